@@ -0,0 +1,236 @@
+use export_sit_timetable_lib::export::ExportFormat;
+use export_sit_timetable_lib::helper::{log_progress, LogLevel};
+use export_sit_timetable_lib::models::{ExportOptions, ScrapOptions};
+use export_sit_timetable_lib::progress::StderrSink;
+use export_sit_timetable_lib::scrap::{extract_timetables, start_scrap};
+use export_sit_timetable_lib::storage::bundled_chromium_path;
+use std::io::BufRead;
+use std::process::ExitCode;
+
+// identifier from tauri.conf.json, the app data dir is named after it
+const APP_IDENTIFIER: &str = "com.whyisthisneeded.app";
+
+const USAGE: &str = "Usage: sit-timetable-cli [OPTIONS]
+
+Logs in to in4SIT, scraps the timetable and writes it to an ICS, CSV, JSON or HTML file.
+
+Credentials are read from the SIT_USERNAME and SIT_PASSWORD environment
+variables, or from stdin (username on the first line, password on the second).
+They are not needed with --manual-login.
+
+Options:
+  -o, --output <PATH>     Path of the file to write [default: timetable.<FORMAT>]
+  -f, --format <FORMAT>   One of ics, csv, json or html [default: ics]
+      --filter-dropped    Exclude dropped classes
+      --filter-waitlisted Exclude waitlisted classes
+      --recurring         Combine weekly repeating classes into recurring events
+      --summary <TEMPLATE>
+                          Event title, e.g. \"{course_code} {type} ({location})\"
+      --description <TEMPLATE>
+                          Event description, e.g. \"{title}\\n{instructor_lines}\"
+      --alarm <MINUTES>   Add a reminder this many minutes before each class
+      --profile <DIR>     Keep the browser profile in DIR so the login is remembered
+      --manual-login      Sign in yourself in the browser window, e.g. for MFA
+      --login-timeout <SECONDS>
+                          How long to wait for a manual sign in [default: 300]
+      --wait-timeout <SECONDS>
+                          How long to wait for each page to load [default: 30]
+      --browser <PATH>    Chrome, Chromium, Edge or Brave executable to use
+      --term <NAME>       Term to export, e.g. \"Trimester 1 2025-26\", repeat it to export
+                          several terms at once [default: the one in4SIT shows]
+      --debug             Show the browser window and wait for it to be closed
+  -h, --help              Print this message
+
+Templates accept {course}, {course_code}, {title}, {section}, {type}, {location},
+{instructors} and {instructor_lines}.";
+
+struct CliArgs {
+    output: String,
+    format: ExportFormat,
+    options: ScrapOptions,
+    export_options: ExportOptions,
+}
+
+fn parse_args() -> Result<Option<CliArgs>, String> {
+    let mut output = None;
+    let mut format = ExportFormat::Ics;
+    let mut options = ScrapOptions {
+        // shared with the app, so Chromium is only downloaded once
+        bundled_browser_dir: dirs::data_dir()
+            .map(|dir| bundled_chromium_path(&dir.join(APP_IDENTIFIER))),
+        ..Default::default()
+    };
+    let mut export_options = ExportOptions::default();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" | "--output" => {
+                output = Some(
+                    args.next()
+                        .ok_or_else(|| format!("{} requires a value", arg))?,
+                );
+            }
+            "-f" | "--format" => {
+                format = args
+                    .next()
+                    .ok_or_else(|| format!("{} requires a value", arg))?
+                    .parse()?;
+            }
+            "--filter-dropped" => export_options.filter_dropped = true,
+            "--filter-waitlisted" => export_options.filter_waitlisted = true,
+            "--recurring" => export_options.compress_recurring = true,
+            "--summary" => {
+                export_options.summary_template = args
+                    .next()
+                    .ok_or_else(|| format!("{} requires a value", arg))?
+                    .replace("\\n", "\n");
+            }
+            "--description" => {
+                export_options.description_template = args
+                    .next()
+                    .ok_or_else(|| format!("{} requires a value", arg))?
+                    .replace("\\n", "\n");
+            }
+            "--alarm" => {
+                let minutes = args
+                    .next()
+                    .ok_or_else(|| format!("{} requires a value", arg))?;
+                let minutes = minutes
+                    .parse()
+                    .map_err(|_| format!("Invalid number of minutes: {}", minutes))?;
+                export_options.alarm_minutes_before = Some(minutes);
+            }
+            "--profile" => {
+                options.user_data_dir = Some(
+                    args.next()
+                        .ok_or_else(|| format!("{} requires a value", arg))?
+                        .into(),
+                );
+            }
+            "--manual-login" => options.manual_login = true,
+            "--login-timeout" => {
+                let seconds = args
+                    .next()
+                    .ok_or_else(|| format!("{} requires a value", arg))?;
+                let seconds = seconds
+                    .parse()
+                    .map_err(|_| format!("Invalid number of seconds: {}", seconds))?;
+                options.manual_login_timeout_secs = Some(seconds);
+            }
+            "--wait-timeout" => {
+                let seconds = args
+                    .next()
+                    .ok_or_else(|| format!("{} requires a value", arg))?;
+                let seconds = seconds
+                    .parse()
+                    .map_err(|_| format!("Invalid number of seconds: {}", seconds))?;
+                options.wait_timeout_secs = Some(seconds);
+            }
+            "--browser" => {
+                options.browser_path = Some(
+                    args.next()
+                        .ok_or_else(|| format!("{} requires a value", arg))?
+                        .into(),
+                );
+            }
+            "--term" => {
+                options.terms.push(
+                    args.next()
+                        .ok_or_else(|| format!("{} requires a value", arg))?,
+                );
+            }
+            "--debug" => options.debug_mode = true,
+            "-h" | "--help" => return Ok(None),
+            _ => return Err(format!("Unknown argument: {}", arg)),
+        }
+    }
+    let exporter = format.exporter();
+    let output = output.unwrap_or_else(|| format!("timetable.{}", exporter.extension()));
+    Ok(Some(CliArgs {
+        output,
+        format,
+        options,
+        export_options,
+    }))
+}
+
+fn read_credentials() -> Result<(String, String), String> {
+    let from_env = (
+        std::env::var("SIT_USERNAME").ok(),
+        std::env::var("SIT_PASSWORD").ok(),
+    );
+    match from_env {
+        (Some(username), Some(password)) => return Ok((username, password)),
+        (Some(_), None) => return Err("SIT_USERNAME is set but SIT_PASSWORD is not".to_string()),
+        (None, Some(_)) => return Err("SIT_PASSWORD is set but SIT_USERNAME is not".to_string()),
+        (None, None) => {}
+    }
+    let mut lines = std::io::stdin().lock().lines();
+    let mut next_line = |name: &str| {
+        lines
+            .next()
+            .transpose()
+            .map_err(|e| e.to_string())?
+            .map(|l| l.trim_end_matches(['\r', '\n']).to_string())
+            .filter(|l| !l.is_empty())
+            .ok_or_else(|| format!("No {} provided on stdin", name))
+    };
+    let username = next_line("username")?;
+    let password = next_line("password")?;
+    Ok((username, password))
+}
+
+async fn run(args: CliArgs) -> Result<(), String> {
+    let (username, password) = if args.options.manual_login {
+        Default::default()
+    } else {
+        read_credentials()?
+    };
+    let pages = start_scrap(&StderrSink, &username, &password, args.options)
+        .await
+        .map_err(|e| e.to_string())?;
+    let (r, courses_info) = extract_timetables(pages, &StderrSink).map_err(|e| e.to_string())?;
+    let data = args
+        .format
+        .exporter()
+        .export(&courses_info, &args.export_options)?;
+    tokio::fs::write(&args.output, data)
+        .await
+        .map_err(|e| e.to_string())?;
+    log_progress(
+        &format!("Wrote {}", args.output),
+        LogLevel::Info,
+        &StderrSink,
+        true,
+    );
+    if r.errors_present {
+        log_progress(
+            "Errors were encountered, verify generated timetable.",
+            LogLevel::Warn,
+            &StderrSink,
+            true,
+        );
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args() {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+    match tauri::async_runtime::block_on(run(args)) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            log_progress(&e, LogLevel::Error, &StderrSink, false);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use chromiumoxide::detection::{default_executable, DetectionOptions};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// What browser a scrape would use, for the "check browser" button.
+#[derive(Serialize, Debug, Clone)]
+pub struct BrowserCheck {
+    pub path: Option<PathBuf>,
+    pub version: Option<String>,
+    // true when `path` is the executable set in settings rather than a discovered one
+    pub configured: bool,
+    pub searched: Vec<PathBuf>,
+}
+
+#[cfg(target_os = "windows")]
+fn known_locations() -> Vec<PathBuf> {
+    let roots = ["PROGRAMFILES", "PROGRAMFILES(X86)", "LOCALAPPDATA"]
+        .iter()
+        .filter_map(std::env::var_os)
+        .map(PathBuf::from)
+        .collect::<Vec<_>>();
+    let executables = [
+        r"Google\Chrome\Application\chrome.exe",
+        r"Chromium\Application\chrome.exe",
+        r"Microsoft\Edge\Application\msedge.exe",
+        r"BraveSoftware\Brave-Browser\Application\brave.exe",
+    ];
+    executables
+        .iter()
+        .flat_map(|exe| roots.iter().map(move |root| root.join(exe)))
+        .collect()
+}
+
+#[cfg(target_os = "macos")]
+fn known_locations() -> Vec<PathBuf> {
+    let apps = [
+        "Google Chrome.app/Contents/MacOS/Google Chrome",
+        "Chromium.app/Contents/MacOS/Chromium",
+        "Microsoft Edge.app/Contents/MacOS/Microsoft Edge",
+        "Brave Browser.app/Contents/MacOS/Brave Browser",
+    ];
+    let mut roots = vec![PathBuf::from("/Applications")];
+    if let Some(home) = std::env::var_os("HOME") {
+        roots.push(PathBuf::from(home).join("Applications"));
+    }
+    apps.iter()
+        .flat_map(|app| roots.iter().map(move |root| root.join(app)))
+        .collect()
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn known_locations() -> Vec<PathBuf> {
+    let names = [
+        "google-chrome",
+        "google-chrome-stable",
+        "chromium",
+        "chromium-browser",
+        "microsoft-edge",
+        "microsoft-edge-stable",
+        "brave-browser",
+    ];
+    let mut dirs = std::env::var_os("PATH")
+        .map(|path| std::env::split_paths(&path).collect::<Vec<_>>())
+        .unwrap_or_default();
+    for dir in ["/usr/bin", "/usr/local/bin", "/snap/bin"] {
+        if !dirs.iter().any(|d| d == Path::new(dir)) {
+            dirs.push(PathBuf::from(dir));
+        }
+    }
+    names
+        .iter()
+        .flat_map(|name| dirs.iter().map(move |dir| dir.join(name)))
+        .collect()
+}
+
+/// The configured executable when there is one, otherwise the first known Chrome, Chromium,
+/// Edge or Brave install. When none of those exist chromiumoxide's own detection is tried,
+/// which also honours the `CHROME` variable and, on Windows, the registry.
+pub fn find_browser(configured: Option<&Path>) -> Option<PathBuf> {
+    match configured {
+        Some(path) => path.is_file().then(|| path.to_path_buf()),
+        None => known_locations()
+            .into_iter()
+            .find(|p| p.is_file())
+            .or_else(|| default_executable(DetectionOptions::default()).ok()),
+    }
+}
+
+/// Chrome on Windows opens a window for `--version`, its version is the name of the folder
+/// next to the executable instead.
+#[cfg(target_os = "windows")]
+pub fn browser_version(path: &Path) -> Option<String> {
+    std::fs::read_dir(path.parent()?)
+        .ok()?
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .filter(|name| name.split('.').all(|part| part.parse::<u32>().is_ok()))
+        .max_by_key(|name| {
+            name.split('.')
+                .map(|part| part.parse::<u32>().unwrap_or_default())
+                .collect::<Vec<_>>()
+        })
+}
+
+#[cfg(not(target_os = "windows"))]
+pub fn browser_version(path: &Path) -> Option<String> {
+    let output = std::process::Command::new(path)
+        .arg("--version")
+        .output()
+        .ok()?;
+    let version = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !version.is_empty()).then_some(version)
+}
+
+pub fn check_browser(configured: Option<&Path>) -> BrowserCheck {
+    let path = find_browser(configured);
+    BrowserCheck {
+        version: path.as_deref().and_then(browser_version),
+        path,
+        configured: configured.is_some(),
+        searched: match configured {
+            Some(path) => vec![path.to_path_buf()],
+            None => known_locations(),
+        },
+    }
+}
@@ -0,0 +1,146 @@
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, CHACHA20_POLY1305, NONCE_LEN};
+use ring::pbkdf2;
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io::{ErrorKind, Write};
+use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
+
+const KEYRING_SERVICE: &str = "export-sit-timetable";
+const KEYRING_USER: &str = "in4sit";
+const FALLBACK_FILE: &str = "credentials.enc";
+const SALT_LEN: usize = 16;
+const PBKDF2_ROUNDS: u32 = 100_000;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Credentials {
+    pub username: String,
+    pub password: String,
+}
+
+// keeps the password out of logs and error messages
+impl fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Credentials")
+            .field("username", &self.username)
+            .field("password", &"<redacted>")
+            .finish()
+    }
+}
+
+fn fallback_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(FALLBACK_FILE)
+}
+
+fn keyring_entry() -> keyring::Result<keyring::Entry> {
+    keyring::Entry::new(KEYRING_SERVICE, KEYRING_USER)
+}
+
+/// Saves to the platform secret store, or to an encrypted file in `data_dir` when there is
+/// none, e.g. on Linux without a running secret service. Any other keyring error is returned,
+/// the credentials are not silently moved to the weaker file. Blocks on the keyring and the
+/// key derivation, call it from a blocking task.
+pub fn save_credentials(data_dir: &Path, credentials: &Credentials) -> Result<(), String> {
+    let json = serde_json::to_string(credentials).map_err(|e| e.to_string())?;
+    match keyring_entry().and_then(|entry| entry.set_password(&json)) {
+        Ok(()) => {
+            // a copy in the weaker fallback must not outlive the keyring one
+            let _ = std::fs::remove_file(fallback_path(data_dir));
+            Ok(())
+        }
+        Err(keyring::Error::NoStorageAccess(_) | keyring::Error::PlatformFailure(_)) => {
+            save_to_file(data_dir, credentials)
+        }
+        Err(e) => Err(format!("Could not save to the keyring: {}", e)),
+    }
+}
+
+/// Blocks like [`save_credentials`].
+pub fn load_credentials(data_dir: &Path) -> Option<Credentials> {
+    keyring_entry()
+        .ok()
+        .and_then(|entry| entry.get_password().ok())
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .or_else(|| load_from_file(data_dir))
+}
+
+/// Removes the credentials from both the secret store and the fallback file.
+pub fn forget_credentials(data_dir: &Path) -> Result<(), String> {
+    if let Ok(entry) = keyring_entry() {
+        match entry.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => {}
+            // nothing can have been saved to a secret store that is not there
+            Err(keyring::Error::NoStorageAccess(_)) => {}
+            Err(e) => return Err(e.to_string()),
+        }
+    }
+    match std::fs::remove_file(fallback_path(data_dir)) {
+        Err(e) if e.kind() != ErrorKind::NotFound => Err(e.to_string()),
+        _ => Ok(()),
+    }
+}
+
+// The key is derived from the machine id, so a copied credentials file cannot be decrypted
+// on another machine. It is no match for someone with access to this machine and account,
+// which is why the platform secret store is always preferred.
+fn machine_key(salt: &[u8]) -> Result<LessSafeKey, String> {
+    let machine_id = ["/etc/machine-id", "/var/lib/dbus/machine-id"]
+        .iter()
+        .find_map(|p| std::fs::read_to_string(p).ok())
+        .ok_or("No secret service or machine id available to protect the credentials")?;
+    let mut key = [0; 32];
+    pbkdf2::derive(
+        pbkdf2::PBKDF2_HMAC_SHA256,
+        NonZeroU32::new(PBKDF2_ROUNDS).unwrap(),
+        salt,
+        machine_id.trim().as_bytes(),
+        &mut key,
+    );
+    let key = UnboundKey::new(&CHACHA20_POLY1305, &key).map_err(|e| e.to_string())?;
+    Ok(LessSafeKey::new(key))
+}
+
+/// Writes the encrypted fallback file: salt, nonce, then the sealed credentials.
+pub fn save_to_file(data_dir: &Path, credentials: &Credentials) -> Result<(), String> {
+    let rng = SystemRandom::new();
+    let mut header = [0; SALT_LEN + NONCE_LEN];
+    rng.fill(&mut header).map_err(|e| e.to_string())?;
+    let (salt, nonce) = header.split_at(SALT_LEN);
+    let nonce = Nonce::try_assume_unique_for_key(nonce).map_err(|e| e.to_string())?;
+    let mut sealed = serde_json::to_vec(credentials).map_err(|e| e.to_string())?;
+    machine_key(salt)?
+        .seal_in_place_append_tag(nonce, Aad::empty(), &mut sealed)
+        .map_err(|e| e.to_string())?;
+
+    std::fs::create_dir_all(data_dir).map_err(|e| e.to_string())?;
+    let mut open_options = std::fs::OpenOptions::new();
+    open_options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        open_options.mode(0o600);
+    }
+    let mut file = open_options
+        .open(fallback_path(data_dir))
+        .map_err(|e| e.to_string())?;
+    file.write_all(&[&header[..], &sealed].concat())
+        .map_err(|e| e.to_string())
+}
+
+/// Returns `None` when there is no fallback file or it cannot be decrypted on this machine.
+pub fn load_from_file(data_dir: &Path) -> Option<Credentials> {
+    let data = std::fs::read(fallback_path(data_dir)).ok()?;
+    if data.len() < SALT_LEN + NONCE_LEN {
+        return None;
+    }
+    let (salt, rest) = data.split_at(SALT_LEN);
+    let (nonce, sealed) = rest.split_at(NONCE_LEN);
+    let nonce = Nonce::try_assume_unique_for_key(nonce).ok()?;
+    let mut sealed = sealed.to_vec();
+    let json = machine_key(salt)
+        .ok()?
+        .open_in_place(nonce, Aad::empty(), &mut sealed)
+        .ok()?;
+    serde_json::from_slice(json).ok()
+}
@@ -0,0 +1,175 @@
+use crate::models::{CourseInfo, TimeTableEntry};
+use chrono::Datelike;
+use serde::Serialize;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Rescheduled,
+    Relocated,
+    InstructorChanged,
+}
+
+/// One meeting that differs between two scrapes. When several things changed at once the
+/// kind is the most disruptive one: rescheduled, then relocated, then instructor changed.
+#[derive(Serialize, Debug, Clone)]
+pub struct EntryChange {
+    pub course_name: String,
+    pub kind: ChangeKind,
+    pub before: Option<TimeTableEntry>,
+    pub after: Option<TimeTableEntry>,
+}
+
+impl EntryChange {
+    pub fn describe(&self) -> String {
+        let fmt = "%a %d/%m %H:%M";
+        match (&self.before, &self.after) {
+            (Some(b), Some(a)) => match self.kind {
+                ChangeKind::Rescheduled => format!(
+                    "{} {} moved from {} to {}",
+                    self.course_name,
+                    a.entry_type.label(),
+                    b.start_datetime.format(fmt),
+                    a.start_datetime.format(fmt)
+                ),
+                ChangeKind::Relocated => format!(
+                    "{} {} on {} moved from {} to {}",
+                    self.course_name,
+                    a.entry_type.label(),
+                    a.start_datetime.format(fmt),
+                    b.location,
+                    a.location
+                ),
+                _ => format!(
+                    "{} {} on {} now taught by {}",
+                    self.course_name,
+                    a.entry_type.label(),
+                    a.start_datetime.format(fmt),
+                    a.instructors.join(", ")
+                ),
+            },
+            (None, Some(a)) => format!(
+                "{} {} added on {}",
+                self.course_name,
+                a.entry_type.label(),
+                a.start_datetime.format(fmt)
+            ),
+            (Some(b), None) => format!(
+                "{} {} on {} removed",
+                self.course_name,
+                b.entry_type.label(),
+                b.start_datetime.format(fmt)
+            ),
+            (None, None) => self.course_name.clone(),
+        }
+    }
+}
+
+struct Flat<'a> {
+    course_name: &'a str,
+    entry: &'a TimeTableEntry,
+}
+
+fn flatten(courses: &[CourseInfo]) -> Vec<Flat<'_>> {
+    let mut flat = courses
+        .iter()
+        .flat_map(|c| {
+            c.table_entries.iter().map(|e| Flat {
+                course_name: &c.course_name,
+                entry: e,
+            })
+        })
+        .collect::<Vec<_>>();
+    flat.sort_by_key(|f| f.entry.start_datetime);
+    flat
+}
+
+fn same_class(a: &Flat, b: &Flat) -> bool {
+    a.course_name == b.course_name
+        && a.entry.class_section == b.entry.class_section
+        && a.entry.entry_type.label() == b.entry.entry_type.label()
+}
+
+fn same_slot(a: &Flat, b: &Flat) -> bool {
+    same_class(a, b)
+        && a.entry.start_datetime == b.entry.start_datetime
+        && a.entry.end_datetime == b.entry.end_datetime
+}
+
+fn identical(a: &Flat, b: &Flat) -> bool {
+    same_slot(a, b)
+        && a.entry.location == b.entry.location
+        && a.entry.instructors == b.entry.instructors
+}
+
+fn same_week(a: &Flat, b: &Flat) -> bool {
+    same_class(a, b) && a.entry.start_datetime.iso_week() == b.entry.start_datetime.iso_week()
+}
+
+/// Compares two scrapes meeting by meeting. Meetings are first paired when nothing changed,
+/// then when only the venue or instructors changed, then when the same class moved within
+/// its week. Whatever is left over was added or removed.
+pub fn diff_timetables(old: &[CourseInfo], new: &[CourseInfo]) -> Vec<EntryChange> {
+    let old = flatten(old);
+    let new = flatten(new);
+    let mut old_used = vec![false; old.len()];
+    let mut new_used = vec![false; new.len()];
+    let mut changes = vec![];
+
+    let passes: [fn(&Flat, &Flat) -> bool; 3] = [identical, same_slot, same_week];
+    for (pass, matches) in passes.iter().enumerate() {
+        for (i, o) in old.iter().enumerate() {
+            if old_used[i] {
+                continue;
+            }
+            let Some(j) = (0..new.len()).find(|&j| !new_used[j] && matches(o, &new[j])) else {
+                continue;
+            };
+            old_used[i] = true;
+            new_used[j] = true;
+            if pass == 0 {
+                continue;
+            }
+            let n = &new[j];
+            let kind = if pass == 2 {
+                ChangeKind::Rescheduled
+            } else if o.entry.location != n.entry.location {
+                ChangeKind::Relocated
+            } else {
+                ChangeKind::InstructorChanged
+            };
+            changes.push(EntryChange {
+                course_name: n.course_name.to_string(),
+                kind,
+                before: Some(o.entry.clone()),
+                after: Some(n.entry.clone()),
+            });
+        }
+    }
+
+    for (o, _) in old.iter().zip(&old_used).filter(|(_, used)| !**used) {
+        changes.push(EntryChange {
+            course_name: o.course_name.to_string(),
+            kind: ChangeKind::Removed,
+            before: Some(o.entry.clone()),
+            after: None,
+        });
+    }
+    for (n, _) in new.iter().zip(&new_used).filter(|(_, used)| !**used) {
+        changes.push(EntryChange {
+            course_name: n.course_name.to_string(),
+            kind: ChangeKind::Added,
+            before: None,
+            after: Some(n.entry.clone()),
+        });
+    }
+    changes.sort_by_key(|c| {
+        c.before
+            .as_ref()
+            .or(c.after.as_ref())
+            .map(|e| e.start_datetime)
+    });
+    changes
+}
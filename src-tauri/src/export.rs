@@ -0,0 +1,381 @@
+use crate::helper::{render_template, PORTAL_TZ};
+use crate::models::{CourseInfo, ExportOptions, ExportSnapshot, ExportedEvent, TimeTableEntry};
+use chrono::{Datelike, Duration, NaiveDate, Utc, Weekday};
+use icalendar::{Calendar, Component, Event, EventStatus, Property};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::str::FromStr;
+
+// Singapore has no daylight saving, a single STANDARD rule covers every date we export.
+const PORTAL_VTIMEZONE: &str = "BEGIN:VTIMEZONE\r
+TZID:Asia/Singapore\r
+X-LIC-LOCATION:Asia/Singapore\r
+BEGIN:STANDARD\r
+TZOFFSETFROM:+0800\r
+TZOFFSETTO:+0800\r
+TZNAME:+08\r
+DTSTART:19700101T000000\r
+END:STANDARD\r
+END:VTIMEZONE\r
+";
+
+/// Turns scraped courses into the contents of an export file.
+pub trait Exporter {
+    fn extension(&self) -> &'static str;
+    fn export(&self, courses: &[CourseInfo], options: &ExportOptions) -> Result<String, String>;
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Ics,
+    Csv,
+    Json,
+    Html,
+}
+
+impl ExportFormat {
+    pub fn exporter(&self) -> Box<dyn Exporter + Send> {
+        match self {
+            ExportFormat::Ics => Box::new(IcsExporter),
+            ExportFormat::Csv => Box::new(CsvExporter),
+            ExportFormat::Json => Box::new(JsonExporter),
+            ExportFormat::Html => Box::new(HtmlExporter),
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "ics" => Ok(ExportFormat::Ics),
+            "csv" => Ok(ExportFormat::Csv),
+            "json" => Ok(ExportFormat::Json),
+            "html" => Ok(ExportFormat::Html),
+            _ => Err(format!("Unknown export format: {}", s)),
+        }
+    }
+}
+
+/// The courses left after the dropped and waitlisted filters of `options`.
+pub fn filter_courses(courses: &[CourseInfo], options: &ExportOptions) -> Vec<CourseInfo> {
+    courses
+        .iter()
+        .filter(|course| options.includes(course))
+        .cloned()
+        .collect()
+}
+
+fn calendar_to_ics(events: Vec<Event>, method: Option<&str>) -> String {
+    let mut calender = Calendar::from_iter(events);
+    if let Some(method) = method {
+        calender.append_property(Property::new("METHOD", method));
+    }
+    calender.append_property(Property::new("X-WR-TIMEZONE", PORTAL_TZ.name()));
+    let mut ics_data = calender.to_string();
+    // icalendar has no VTIMEZONE component, so the definition is placed ahead of the events
+    let insert_at = ics_data
+        .find("BEGIN:VEVENT")
+        .or_else(|| ics_data.find("END:VCALENDAR"))
+        .unwrap_or(ics_data.len());
+    ics_data.insert_str(insert_at, PORTAL_VTIMEZONE);
+    ics_data
+}
+
+fn event_uid(event: &Event) -> String {
+    event.get_uid().unwrap_or_default().to_string()
+}
+
+// Lines that differ on every export even when the event itself did not change. Alarms get
+// a random UID, so UID lines are dropped as well, the event UID is the snapshot key anyway.
+fn event_content(event: &Event) -> String {
+    Component::to_string(event)
+        .split("\r\n")
+        .filter(|l| {
+            !["DTSTAMP", "SEQUENCE", "UID"]
+                .iter()
+                .any(|p| l.starts_with(p))
+        })
+        .collect::<Vec<_>>()
+        .join("\r\n")
+}
+
+pub struct IcsExport {
+    pub ics: String,
+    /// Cancellations of the events removed since the previous export. iTIP allows a single
+    /// METHOD per calendar, so these are written to a file of their own.
+    pub cancelled_ics: Option<String>,
+    pub snapshot: ExportSnapshot,
+}
+
+/// `previous` is the snapshot of the last export to the same file. An event keeps its SEQUENCE
+/// from it while unchanged and gets the next one when it changed. With `options.incremental`
+/// only the new and changed events are written and events that disappeared are cancelled,
+/// otherwise every event is written.
+pub fn export_ics(
+    courses: &[CourseInfo],
+    options: &ExportOptions,
+    previous: Option<&ExportSnapshot>,
+) -> IcsExport {
+    // a course filtered out since the previous export is cancelled like a removed one
+    let courses = &filter_courses(courses, options);
+    let exported_at = Utc::now();
+    let events: Vec<Event> = courses
+        .iter()
+        .flat_map(|course| course.to_events(exported_at, options))
+        .collect();
+    let mut snapshot = ExportSnapshot {
+        exported_at,
+        courses: courses.to_vec(),
+        events: BTreeMap::new(),
+    };
+
+    let mut all = vec![];
+    let mut changed = vec![];
+    for mut event in events {
+        let uid = event_uid(&event);
+        let content = event_content(&event);
+        let (sequence, is_changed) = match previous.and_then(|p| p.events.get(&uid)) {
+            Some(before) if before.content == content => (before.sequence, false),
+            Some(before) => (before.sequence + 1, true),
+            None => (0, true),
+        };
+        event.sequence(sequence);
+        if is_changed {
+            changed.push(event.clone());
+        }
+        all.push(event);
+        snapshot
+            .events
+            .insert(uid, ExportedEvent { sequence, content });
+    }
+
+    let Some(previous) = previous.filter(|_| options.incremental) else {
+        return IcsExport {
+            ics: calendar_to_ics(all, None),
+            cancelled_ics: None,
+            snapshot,
+        };
+    };
+
+    // the removed events are rebuilt from the previous courses to keep their summary and times
+    let previous_events: BTreeMap<String, Event> = previous
+        .courses
+        .iter()
+        .flat_map(|course| course.to_events(exported_at, options))
+        .map(|event| (event_uid(&event), event))
+        .collect();
+    let cancelled: Vec<Event> = previous
+        .events
+        .iter()
+        .filter(|(uid, _)| !snapshot.events.contains_key(*uid))
+        .map(|(uid, before)| {
+            let mut event = previous_events.get(uid).cloned().unwrap_or_else(|| {
+                let mut event = Event::new();
+                event.uid(uid).timestamp(exported_at);
+                event
+            });
+            event
+                .sequence(before.sequence + 1)
+                .status(EventStatus::Cancelled)
+                .done()
+        })
+        .collect();
+
+    IcsExport {
+        ics: calendar_to_ics(changed, Some("PUBLISH")),
+        cancelled_ics: (!cancelled.is_empty()).then(|| calendar_to_ics(cancelled, Some("CANCEL"))),
+        snapshot,
+    }
+}
+
+pub fn courses_to_ics(courses: &[CourseInfo], options: &ExportOptions) -> String {
+    export_ics(courses, options, None).ics
+}
+
+pub struct IcsExporter;
+
+impl Exporter for IcsExporter {
+    fn extension(&self) -> &'static str {
+        "ics"
+    }
+
+    fn export(&self, courses: &[CourseInfo], options: &ExportOptions) -> Result<String, String> {
+        Ok(courses_to_ics(courses, options))
+    }
+}
+
+pub struct JsonExporter;
+
+impl Exporter for JsonExporter {
+    fn extension(&self) -> &'static str {
+        "json"
+    }
+
+    fn export(&self, courses: &[CourseInfo], options: &ExportOptions) -> Result<String, String> {
+        serde_json::to_string_pretty(&filter_courses(courses, options)).map_err(|e| e.to_string())
+    }
+}
+
+#[derive(Serialize)]
+struct CsvRow<'a> {
+    course: &'a str,
+    subject: Option<&'a str>,
+    catalog_number: Option<&'a str>,
+    title: Option<&'a str>,
+    units: Option<f32>,
+    grading_basis: Option<&'a str>,
+    enrollment_status: Option<&'a str>,
+    entry_type: &'a str,
+    class_number: Option<&'a str>,
+    class_section: &'a str,
+    date: String,
+    day: String,
+    start: String,
+    end: String,
+    location: &'a str,
+    instructors: String,
+}
+
+pub struct CsvExporter;
+
+impl Exporter for CsvExporter {
+    fn extension(&self) -> &'static str {
+        "csv"
+    }
+
+    fn export(&self, courses: &[CourseInfo], options: &ExportOptions) -> Result<String, String> {
+        let mut writer = csv::Writer::from_writer(vec![]);
+        for course in courses.iter().filter(|c| options.includes(c)) {
+            for e in &course.table_entries {
+                writer
+                    .serialize(CsvRow {
+                        course: &course.course_name,
+                        subject: course.subject.as_deref(),
+                        catalog_number: course.catalog_number.as_deref(),
+                        title: course.title.as_deref(),
+                        units: course.units,
+                        grading_basis: course.grading_basis.as_deref(),
+                        enrollment_status: course.enrollment_status.as_ref().map(|s| s.label()),
+                        entry_type: e.entry_type.label(),
+                        class_number: e.class_number.as_deref(),
+                        class_section: &e.class_section,
+                        date: e.start_datetime.format("%Y-%m-%d").to_string(),
+                        day: e.start_datetime.format("%a").to_string(),
+                        start: e.start_datetime.format("%H:%M").to_string(),
+                        end: e.end_datetime.format("%H:%M").to_string(),
+                        location: &e.location,
+                        instructors: e.instructors.join("; "),
+                    })
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+        let data = writer.into_inner().map_err(|e| e.to_string())?;
+        String::from_utf8(data).map_err(|e| e.to_string())
+    }
+}
+
+const HTML_STYLE: &str = "body { font-family: sans-serif; margin: 1.5em; }
+h1 { font-size: 1.4em; }
+section { break-inside: avoid; margin-bottom: 2em; }
+h2 { font-size: 1.1em; margin-bottom: 0.4em; }
+table { border-collapse: collapse; width: 100%; table-layout: fixed; }
+th, td { border: 1px solid #999; padding: 4px; vertical-align: top; }
+th { background: #eee; }
+.entry { border-left: 4px solid #555; padding: 2px 4px; margin-bottom: 4px; font-size: 0.85em; }
+.time { display: block; font-weight: bold; }
+@media print { section { break-after: page; } }";
+
+fn escape_html(input: &str) -> String {
+    input
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Printable weekly grid, one table per week with a column per day.
+pub struct HtmlExporter;
+
+impl Exporter for HtmlExporter {
+    fn extension(&self) -> &'static str {
+        "html"
+    }
+
+    fn export(&self, courses: &[CourseInfo], options: &ExportOptions) -> Result<String, String> {
+        let mut weeks: BTreeMap<NaiveDate, Vec<(&CourseInfo, &TimeTableEntry)>> = BTreeMap::new();
+        for course in courses.iter().filter(|c| options.includes(c)) {
+            for e in &course.table_entries {
+                let date = e.start_datetime.date_naive();
+                let monday = date - Duration::days(date.weekday().num_days_from_monday().into());
+                weeks.entry(monday).or_default().push((course, e));
+            }
+        }
+        let has_weekday = |day: Weekday| {
+            weeks
+                .values()
+                .flatten()
+                .any(|(_, e)| e.start_datetime.weekday() == day)
+        };
+        let day_count = if has_weekday(Weekday::Sun) {
+            7
+        } else if has_weekday(Weekday::Sat) {
+            6
+        } else {
+            5
+        };
+
+        let mut out = String::new();
+        let _ = write!(
+            out,
+            "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+             <title>Timetable</title>\n<style>\n{}\n</style>\n</head>\n<body>\n<h1>Timetable</h1>\n",
+            HTML_STYLE
+        );
+        for (monday, mut entries) in weeks {
+            entries.sort_by_key(|(_, e)| e.start_datetime);
+            let _ = writeln!(
+                out,
+                "<section>\n<h2>Week of {}</h2>\n<table>\n<tr>",
+                monday.format("%d %b %Y")
+            );
+            let days = (0..day_count)
+                .map(|i| monday + Duration::days(i))
+                .collect::<Vec<_>>();
+            for day in &days {
+                let _ = write!(out, "<th>{}</th>", day.format("%a %d/%m"));
+            }
+            out.push_str("</tr>\n<tr>");
+            for day in &days {
+                out.push_str("<td>");
+                for (course, e) in entries
+                    .iter()
+                    .filter(|(_, e)| e.start_datetime.date_naive() == *day)
+                {
+                    let color = options
+                        .entry_type_styles
+                        .get(e.entry_type.label())
+                        .and_then(|s| s.color.as_deref())
+                        .map(|c| format!(" style=\"border-left-color: {}\"", escape_html(c)))
+                        .unwrap_or_default();
+                    let _ = write!(
+                        out,
+                        "<div class=\"entry\"{}><span class=\"time\">{} - {}</span>{}<br>{}</div>",
+                        color,
+                        e.start_datetime.format("%H:%M"),
+                        e.end_datetime.format("%H:%M"),
+                        escape_html(&render_template(&options.summary_template, course, e)),
+                        escape_html(&e.location),
+                    );
+                }
+                out.push_str("</td>");
+            }
+            out.push_str("</tr>\n</table>\n</section>\n");
+        }
+        out.push_str("</body>\n</html>\n");
+        Ok(out)
+    }
+}
@@ -0,0 +1,209 @@
+use crate::helper::{log_progress, LogLevel};
+use crate::progress::ProgressSink;
+use ring::digest::{Context, SHA256};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tokio::io::AsyncWriteExt;
+
+const MANIFEST_FILE: &str = "installed.json";
+const SNAPSHOT_HOST: &str = "https://storage.googleapis.com/chromium-browser-snapshots";
+
+// The snapshot installed on each platform and the SHA-256 of its archive, recorded with
+// `sha256sum` from the archive URL built in `ensure_bundled_chromium`. Every entry of
+// `PLATFORMS` needs one, a platform missing here is never installed.
+const PINNED: &[Pin] = &[];
+
+struct Pin {
+    platform: &'static str,
+    revision: &'static str,
+    sha256: &'static str,
+}
+
+/// Where the snapshots of a platform live and what is in their archive.
+pub struct Platform {
+    pub os: &'static str,
+    pub arch: &'static str,
+    pub name: &'static str,
+    pub archive: &'static str,
+    pub executable: &'static str,
+}
+
+/// The platforms a bundled Chromium is offered for.
+pub const PLATFORMS: &[Platform] = &[
+    Platform {
+        os: "linux",
+        arch: "x86_64",
+        name: "Linux_x64",
+        archive: "chrome-linux.zip",
+        executable: "chrome-linux/chrome",
+    },
+    Platform {
+        os: "macos",
+        arch: "x86_64",
+        name: "Mac",
+        archive: "chrome-mac.zip",
+        executable: "chrome-mac/Chromium.app/Contents/MacOS/Chromium",
+    },
+    Platform {
+        os: "macos",
+        arch: "aarch64",
+        name: "Mac_Arm",
+        archive: "chrome-mac.zip",
+        executable: "chrome-mac/Chromium.app/Contents/MacOS/Chromium",
+    },
+    Platform {
+        os: "windows",
+        arch: "x86_64",
+        name: "Win_x64",
+        archive: "chrome-win.zip",
+        executable: "chrome-win/chrome.exe",
+    },
+    Platform {
+        os: "windows",
+        arch: "x86",
+        name: "Win",
+        archive: "chrome-win.zip",
+        executable: "chrome-win/chrome.exe",
+    },
+];
+
+fn platform() -> Option<&'static Platform> {
+    PLATFORMS
+        .iter()
+        .find(|p| p.os == std::env::consts::OS && p.arch == std::env::consts::ARCH)
+}
+
+/// The pinned (revision, sha256) of a platform's snapshot.
+pub fn pinned_snapshot(platform: &str) -> Option<(&'static str, &'static str)> {
+    PINNED
+        .iter()
+        .find(|pin| pin.platform == platform)
+        .map(|pin| (pin.revision, pin.sha256))
+}
+
+/// Written once the archive passed its checksum and was extracted. Launches only compare the
+/// revision and executable size, the archive checksum is what vouches for the contents.
+#[derive(Serialize, Deserialize, Debug)]
+struct Manifest {
+    revision: String,
+    executable: PathBuf,
+    size: u64,
+}
+
+/// Returns the executable of a complete download of the pinned revision.
+pub fn installed_bundled_chromium(dir: &Path) -> Option<PathBuf> {
+    let (revision, _) = pinned_snapshot(platform()?.name)?;
+    let manifest = std::fs::read_to_string(dir.join(MANIFEST_FILE)).ok()?;
+    let manifest: Manifest = serde_json::from_str(&manifest).ok()?;
+    let size = std::fs::metadata(&manifest.executable).ok()?.len();
+    let matches = manifest.revision == revision && size == manifest.size;
+    matches.then_some(manifest.executable)
+}
+
+/// Streams the archive to `path`, returning its SHA-256.
+async fn download(url: &str, path: &Path, sink: &dyn ProgressSink) -> Result<String, String> {
+    let mut response = reqwest::get(url)
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| e.to_string())?;
+    let total = response.content_length();
+    let mut file = tokio::fs::File::create(path)
+        .await
+        .map_err(|e| e.to_string())?;
+    let mut context = Context::new(&SHA256);
+    let mut downloaded = 0;
+    let mut reported = Instant::now();
+    while let Some(chunk) = response.chunk().await.map_err(|e| e.to_string())? {
+        context.update(&chunk);
+        file.write_all(&chunk).await.map_err(|e| e.to_string())?;
+        downloaded += chunk.len() as u64;
+        if reported.elapsed() >= Duration::from_secs(2) {
+            let msg = match total {
+                Some(total) => format!(
+                    "Downloaded {} of {} MB",
+                    downloaded / 1_000_000,
+                    total / 1_000_000
+                ),
+                None => format!("Downloaded {} MB", downloaded / 1_000_000),
+            };
+            log_progress(&msg, LogLevel::Info, sink, true);
+            reported = Instant::now();
+        }
+    }
+    file.flush().await.map_err(|e| e.to_string())?;
+    let mut hex = String::new();
+    for byte in context.finish().as_ref() {
+        let _ = write!(hex, "{:02x}", byte);
+    }
+    Ok(hex)
+}
+
+/// Downloads the Chromium revision pinned for this platform into `dir`, unless it is already
+/// there, reporting progress while it downloads. The archive is checked against its pinned
+/// checksum before anything is extracted.
+pub async fn ensure_bundled_chromium(
+    dir: &Path,
+    sink: &dyn ProgressSink,
+) -> Result<PathBuf, String> {
+    if let Some(executable) = installed_bundled_chromium(dir) {
+        return Ok(executable);
+    }
+    let platform = platform().ok_or("No Chromium snapshots are available for this platform")?;
+    let (revision, expected) = pinned_snapshot(platform.name)
+        .ok_or_else(|| format!("No Chromium snapshot is pinned for {}", platform.name))?;
+    // whatever is there is incomplete or outdated
+    match tokio::fs::remove_dir_all(dir).await {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.to_string()),
+        _ => {}
+    }
+    tokio::fs::create_dir_all(dir)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let msg = format!("Downloading Chromium {}", revision);
+    log_progress(&msg, LogLevel::Info, sink, true);
+    let url = format!(
+        "{}/{}/{}/{}",
+        SNAPSHOT_HOST, platform.name, revision, platform.archive
+    );
+    let archive = dir.join(platform.archive);
+    let sha256 = download(&url, &archive, sink).await?;
+    if sha256 != expected {
+        let _ = tokio::fs::remove_file(&archive).await;
+        return Err(format!(
+            "The Chromium download does not match its pinned checksum, expected {} but got {}",
+            expected, sha256
+        ));
+    }
+
+    log_progress("Extracting Chromium", LogLevel::Info, sink, true);
+    let extract_to = dir.to_path_buf();
+    let extract_from = archive.clone();
+    tokio::task::spawn_blocking(move || {
+        let file = std::fs::File::open(extract_from).map_err(|e| e.to_string())?;
+        let mut zip = zip::ZipArchive::new(file).map_err(|e| e.to_string())?;
+        zip.extract(extract_to).map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+    let _ = tokio::fs::remove_file(&archive).await;
+
+    let executable = dir.join(platform.executable);
+    let size = tokio::fs::metadata(&executable)
+        .await
+        .map_err(|e| format!("{} is missing from the archive: {}", platform.executable, e))?
+        .len();
+    let manifest = Manifest {
+        revision: revision.to_string(),
+        executable,
+        size,
+    };
+    let json = serde_json::to_string(&manifest).map_err(|e| e.to_string())?;
+    tokio::fs::write(dir.join(MANIFEST_FILE), json)
+        .await
+        .map_err(|e| e.to_string())?;
+    log_progress("Chromium is ready", LogLevel::Info, sink, true);
+    Ok(manifest.executable)
+}
@@ -1,14 +1,107 @@
-use crate::helper::{log_to_front, LogLevel};
-use crate::models::{ScrapOptions, ScrapResult};
-use crate::scrap::{extract_timetable_from_html, start_scrap};
+use crate::browser::{self, BrowserCheck};
+use crate::credentials::{self, Credentials};
+use crate::diff::{diff_timetables, EntryChange};
+use crate::export::{courses_to_ics, export_ics, ExportFormat};
+use crate::helper::{log_progress, LogLevel};
+use crate::models::{
+    CourseInfo, ExportOptions, SavedSchedule, SavedSubscription, SavedTimetable, ScrapOptions,
+    ScrapResult, ScrapeMetadata, SubscriptionOptions, Term,
+};
+use crate::scheduler::{RescrapeSchedule, DEFAULT_INTERVAL_HOURS};
+use crate::scrap::{extract_timetables, fetch_terms, import_saved_html, start_scrap, test_login};
+use crate::storage::{
+    browser_profile_path, bundled_chromium_path, cancelled_ics_path, clear_browser_profile, clear_last_scrape,
+    clear_schedule, load_export_snapshot, load_schedule, load_snapshot_file, load_subscription,
+    save_export_snapshot, save_last_scrape, save_schedule, save_subscription,
+};
+use crate::subscription;
 use crate::AppState;
-use icalendar::{Calendar, Event};
-use tauri::{AppHandle, State};
+use chrono::Utc;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager, State};
 use tauri_plugin_dialog::{DialogExt, MessageDialogKind};
 use tauri_plugin_shell::ShellExt;
-use tokio::sync::Mutex;
+use tokio::sync::{Mutex, OwnedMutexGuard};
 use crate::updater::get_latest_release;
 
+/// Waits until no other scrape is using the browser, a scheduled re-scrape and one started
+/// by the user would otherwise both open the persistent browser profile.
+pub(crate) async fn lock_browser(app: &AppHandle) -> OwnedMutexGuard<()> {
+    let lock = app.state::<Mutex<AppState>>().lock().await.browser_lock.clone();
+    match lock.clone().try_lock_owned() {
+        Ok(guard) => guard,
+        Err(_) => {
+            log_progress("Waiting for the running scrape to finish", LogLevel::Info, app, true);
+            lock.lock_owned().await
+        }
+    }
+}
+
+/// Replaces the timetable in the app state and saves it for the next start, returning what
+/// changed since the previous timetable. Failing to save is only reported, the scrape itself
+/// still succeeded.
+pub(crate) async fn store_scrape(
+    app: &AppHandle,
+    state_v: &mut AppState,
+    courses: Vec<CourseInfo>,
+    term: Option<String>,
+    options: Option<ScrapOptions>,
+) -> Vec<EntryChange> {
+    let changes = if state_v.scrapped_info.is_empty() {
+        vec![]
+    } else {
+        diff_timetables(&state_v.scrapped_info, &courses)
+    };
+    if !changes.is_empty() {
+        let msg = format!("{} classes changed since the last scrape", changes.len());
+        log_progress(&msg, LogLevel::Warn, app, true);
+        for change in &changes {
+            log_progress(&change.describe(), LogLevel::Warn, app, false);
+        }
+    }
+    let saved = SavedTimetable {
+        metadata: ScrapeMetadata {
+            scraped_at: Utc::now(),
+            term,
+            options,
+        },
+        courses,
+    };
+    let persisted = match app.path().app_data_dir() {
+        Ok(dir) => save_last_scrape(&dir, &saved).await,
+        Err(e) => Err(e.to_string()),
+    };
+    if let Err(e) = persisted {
+        let msg = format!("Could not save timetable for next start: {}", e);
+        log_progress(&msg, LogLevel::Warn, app, true);
+    }
+    state_v.scrapped_info = saved.courses;
+    state_v.last_scrape = Some(saved.metadata);
+    changes
+}
+
+/// Points the browser at the persistent profile when the session should be kept, and at
+/// the bundled Chromium download.
+pub(crate) fn with_app_paths(app: &AppHandle, mut options: ScrapOptions) -> ScrapOptions {
+    if let Ok(dir) = app.path().app_data_dir() {
+        if options.persist_session {
+            options.user_data_dir = Some(browser_profile_path(&dir));
+        }
+        options.bundled_browser_dir = Some(bundled_chromium_path(&dir));
+    }
+    options
+}
+
+// the keyring and the fallback key derivation block, so they run off the async runtime
+pub(crate) async fn saved_credentials(app: &AppHandle) -> Result<Credentials, String> {
+    let dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    tokio::task::spawn_blocking(move || credentials::load_credentials(&dir))
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or("No saved credentials".to_string())
+}
+
 #[tauri::command]
 pub async fn handle_credentials(
     app: AppHandle,
@@ -17,19 +110,68 @@ pub async fn handle_credentials(
     password: String,
     options: ScrapOptions,
 ) -> Result<ScrapResult, String> {
-    let html = start_scrap(&app, &username, &password, options)
+    // an empty form means "use the remembered credentials", unless signing in by hand
+    let Credentials { username, password } = if password.is_empty() && !options.manual_login {
+        saved_credentials(&app).await?
+    } else {
+        Credentials { username, password }
+    };
+    let options = with_app_paths(&app, options);
+    let browser = lock_browser(&app).await;
+    let pages = start_scrap(&app, &username, &password, options.clone())
         .await
         .map_err(|e| {
-            log_to_front(&e.to_string(), LogLevel::Error, &app, false);
+            log_progress(&e.to_string(), LogLevel::Error, &app, false);
             e.to_string()
         })?;
-    let (r, courses_info) = extract_timetable_from_html(html, &app).map_err(|e| {
-        log_to_front(&e.to_string(), LogLevel::Error, &app, false);
+    drop(browser);
+    let (mut r, courses_info) = extract_timetables(pages, &app).map_err(|e| {
+        log_progress(&e.to_string(), LogLevel::Error, &app, false);
         e.to_string()
     })?;
     let mut state_v = state.lock().await;
-    state_v.scrapped_info = courses_info;
-    log_to_front("Done!", LogLevel::Info, &app, true);
+    let term = r.term.clone();
+    r.changes = store_scrape(&app, &mut state_v, courses_info, term, Some(options)).await;
+    log_progress("Done!", LogLevel::Info, &app, true);
+    Ok(r)
+}
+
+/// Signs in and lists the terms the student can pick for `ScrapOptions::term`.
+#[tauri::command]
+pub async fn get_available_terms(
+    app: AppHandle,
+    username: String,
+    password: String,
+    options: ScrapOptions,
+) -> Result<Vec<Term>, String> {
+    let Credentials { username, password } = if password.is_empty() && !options.manual_login {
+        saved_credentials(&app).await?
+    } else {
+        Credentials { username, password }
+    };
+    let options = with_app_paths(&app, options);
+    let _browser = lock_browser(&app).await;
+    fetch_terms(&app, &username, &password, options)
+        .await
+        .map_err(|e| {
+            log_progress(&e.to_string(), LogLevel::Error, &app, false);
+            e.to_string()
+        })
+}
+
+#[tauri::command]
+pub async fn import_html(
+    app: AppHandle,
+    state: State<'_, Mutex<AppState>>,
+    path: String,
+) -> Result<ScrapResult, String> {
+    let (mut r, courses_info) = import_saved_html(&path, &app).await.map_err(|e| {
+        log_progress(&e.to_string(), LogLevel::Error, &app, false);
+        e.to_string()
+    })?;
+    let mut state_v = state.lock().await;
+    r.changes = store_scrape(&app, &mut state_v, courses_info, r.term.clone(), None).await;
+    log_progress("Done!", LogLevel::Info, &app, true);
     Ok(r)
 }
 
@@ -37,21 +179,363 @@ pub async fn handle_credentials(
 pub async fn export_to_ics(
     state: State<'_, Mutex<AppState>>,
     path: String,
+    options: Option<ExportOptions>,
 ) -> Result<(), String> {
+    let options = options.unwrap_or_default();
+    let path = Path::new(&path);
+    // the snapshot also keeps the SEQUENCE of unchanged events in a full export
+    let previous = load_export_snapshot(path).await;
     let state_v = state.lock().await;
-    let courses = state_v.scrapped_info.clone();
-    let combined_events: Vec<Event> = courses
-        .into_iter()
-        .flat_map(|course_info| Into::<Vec<Event>>::into(course_info))
-        .collect();
-    let calender = Calendar::from_iter(combined_events);
-    let ics_data = calender.to_string();
-    tokio::fs::write(path, ics_data)
+    let export = export_ics(&state_v.scrapped_info, &options, previous.as_ref());
+    drop(state_v);
+    tokio::fs::write(path, export.ics)
         .await
         .map_err(|e| e.to_string())?;
+    // an older cancellation file must not be imported again alongside this export
+    let cancelled_path = cancelled_ics_path(path);
+    match export.cancelled_ics {
+        Some(cancelled) => tokio::fs::write(&cancelled_path, cancelled)
+            .await
+            .map_err(|e| e.to_string())?,
+        None => match tokio::fs::remove_file(&cancelled_path).await {
+            Err(e) if e.kind() != ErrorKind::NotFound => return Err(e.to_string()),
+            _ => {}
+        },
+    }
+    save_export_snapshot(path, &export.snapshot).await
+}
+
+#[tauri::command]
+pub async fn export_timetable(
+    state: State<'_, Mutex<AppState>>,
+    path: String,
+    format: ExportFormat,
+    options: Option<ExportOptions>,
+) -> Result<(), String> {
+    let options = options.unwrap_or_default();
+    let state_v = state.lock().await;
+    let data = format
+        .exporter()
+        .export(&state_v.scrapped_info, &options)?;
+    tokio::fs::write(path, data)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Compares two timetable snapshots, or one snapshot against the timetable currently loaded
+/// in the app when `new_path` is not given.
+#[tauri::command]
+pub async fn diff_snapshots(
+    state: State<'_, Mutex<AppState>>,
+    old_path: String,
+    new_path: Option<String>,
+) -> Result<Vec<EntryChange>, String> {
+    let old = load_snapshot_file(Path::new(&old_path)).await?;
+    let new = match new_path {
+        Some(p) => load_snapshot_file(Path::new(&p)).await?,
+        None => state.lock().await.scrapped_info.clone(),
+    };
+    Ok(diff_timetables(&old, &new))
+}
+
+/// Serves the timetable with the given token and records the server, so the next start of the
+/// app brings it back at the same URL.
+pub(crate) async fn serve_subscription(
+    app: &AppHandle,
+    state_v: &mut AppState,
+    options: &SubscriptionOptions,
+    token: &str,
+    export_options: ExportOptions,
+) -> Result<String, String> {
+    if let Some(server) = state_v.subscription_server.take() {
+        server.stop().await;
+    }
+    let calendar_app = app.clone();
+    let calendar_options = export_options.clone();
+    let server = subscription::start_subscription_server(options, token, move || {
+        let app = calendar_app.clone();
+        let export_options = calendar_options.clone();
+        async move {
+            let state = app.state::<Mutex<AppState>>();
+            let state_v = state.lock().await;
+            courses_to_ics(&state_v.scrapped_info, &export_options)
+        }
+    })
+    .await?;
+    let saved = SavedSubscription {
+        token: server.token.clone(),
+        port: server.port,
+        allow_lan: options.allow_lan,
+        export_options,
+        running: true,
+    };
+    let url = server.url.clone();
+    state_v.subscription_server = Some(server);
+    let dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    if let Err(e) = save_subscription(&dir, &saved).await {
+        let msg = format!("Could not save the subscription for the next start: {}", e);
+        log_progress(&msg, LogLevel::Warn, app, true);
+    }
+    Ok(url)
+}
+
+/// Brings back the subscription server and the re-scrape schedule that were running when the
+/// app was last closed.
+pub(crate) async fn restore_background_tasks(app: AppHandle) {
+    let Ok(dir) = app.path().app_data_dir() else {
+        return;
+    };
+    let state = app.state::<Mutex<AppState>>();
+    let mut state_v = state.lock().await;
+    if let Some(saved) = load_subscription(&dir).filter(|saved| saved.running) {
+        let options = SubscriptionOptions {
+            port: saved.port,
+            allow_lan: saved.allow_lan,
+        };
+        let restored = serve_subscription(
+            &app,
+            &mut state_v,
+            &options,
+            &saved.token,
+            saved.export_options,
+        )
+        .await;
+        if let Err(e) = restored {
+            let msg = format!("Could not restore the calendar subscription: {}", e);
+            log_progress(&msg, LogLevel::Error, &app, true);
+        }
+    }
+    if let Some(saved) = load_schedule(&dir) {
+        schedule_rescrape(&app, &mut state_v, None, saved.interval_hours);
+    }
+}
+
+/// Starts serving the timetable for calendar apps to subscribe to, replacing a server that is
+/// already running. Returns the subscription URL, which stays the same across restarts.
+#[tauri::command]
+pub async fn start_subscription_server(
+    app: AppHandle,
+    state: State<'_, Mutex<AppState>>,
+    options: Option<SubscriptionOptions>,
+    export_options: Option<ExportOptions>,
+) -> Result<String, String> {
+    let mut options = options.unwrap_or_default();
+    let dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    let saved = load_subscription(&dir);
+    // a port picked by the OS last time is asked for again, so the URL does not change
+    if options.port == 0 {
+        options.port = saved.as_ref().map_or(0, |saved| saved.port);
+    }
+    let token = saved.map_or_else(subscription::new_token, |saved| saved.token);
+    let mut state_v = state.lock().await;
+    serve_subscription(
+        &app,
+        &mut state_v,
+        &options,
+        &token,
+        export_options.unwrap_or_default(),
+    )
+    .await
+}
+
+#[tauri::command]
+pub async fn stop_subscription_server(
+    app: AppHandle,
+    state: State<'_, Mutex<AppState>>,
+) -> Result<(), String> {
+    if let Some(server) = state.lock().await.subscription_server.take() {
+        server.stop().await;
+    }
+    let dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    if let Some(mut saved) = load_subscription(&dir) {
+        saved.running = false;
+        save_subscription(&dir, &saved).await?;
+    }
     Ok(())
 }
 
+#[tauri::command]
+pub async fn get_subscription_url(state: State<'_, Mutex<AppState>>) -> Result<Option<String>, ()> {
+    let state_v = state.lock().await;
+    Ok(state_v
+        .subscription_server
+        .as_ref()
+        .map(|server| server.url.clone()))
+}
+
+/// Re-scrapes every `interval_hours` (daily by default) while the app is open, replacing a
+/// schedule that is already running. There is no tray icon, the schedule stops when the window
+/// is closed and resumes on the next start, catching up on a re-scrape that fell due meanwhile.
+/// Without a username and password the saved credentials are used, and a resumed schedule
+/// always uses the saved ones. Changes are reported through a `timetable-changed` event
+/// and a desktop notification.
+#[tauri::command]
+pub async fn start_scheduled_rescrape(
+    app: AppHandle,
+    state: State<'_, Mutex<AppState>>,
+    username: Option<String>,
+    password: Option<String>,
+    interval_hours: Option<u32>,
+) -> Result<(), String> {
+    let interval_hours = interval_hours.unwrap_or(DEFAULT_INTERVAL_HOURS).max(1);
+    let credentials = match (username, password) {
+        (Some(username), Some(password)) => Some(Credentials { username, password }),
+        _ => None,
+    };
+    let mut state_v = state.lock().await;
+    schedule_rescrape(&app, &mut state_v, credentials, interval_hours);
+    let dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    save_schedule(&dir, &SavedSchedule { interval_hours }).await
+}
+
+pub(crate) fn schedule_rescrape(
+    app: &AppHandle,
+    state_v: &mut AppState,
+    credentials: Option<Credentials>,
+    interval_hours: u32,
+) {
+    if let Some(schedule) = state_v.rescrape_schedule.take() {
+        schedule.stop();
+    }
+    let msg = format!(
+        "Re-scraping every {} hours while the app is open",
+        interval_hours
+    );
+    log_progress(&msg, LogLevel::Info, app, true);
+    let schedule = RescrapeSchedule::start(app.clone(), credentials, interval_hours);
+    state_v.rescrape_schedule = Some(schedule);
+}
+
+#[tauri::command]
+pub async fn stop_scheduled_rescrape(
+    app: AppHandle,
+    state: State<'_, Mutex<AppState>>,
+) -> Result<(), String> {
+    if let Some(schedule) = state.lock().await.rescrape_schedule.take() {
+        schedule.stop();
+    }
+    let dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    clear_schedule(&dir).await
+}
+
+/// Returns the interval of the running schedule, if any.
+#[tauri::command]
+pub async fn get_scheduled_rescrape(state: State<'_, Mutex<AppState>>) -> Result<Option<u32>, ()> {
+    let state_v = state.lock().await;
+    Ok(state_v.rescrape_schedule.as_ref().map(|s| s.interval_hours))
+}
+
+/// Remembers the credentials in the OS keyring, never in the saved timetable or the logs.
+#[tauri::command]
+pub async fn save_credentials(
+    app: AppHandle,
+    username: String,
+    password: String,
+) -> Result<(), String> {
+    let dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    let saved = Credentials { username, password };
+    tokio::task::spawn_blocking(move || credentials::save_credentials(&dir, &saved))
+        .await
+        .map_err(|e| e.to_string())??;
+    log_progress("Credentials saved", LogLevel::Info, &app, true);
+    Ok(())
+}
+
+/// Signs in with the given credentials, or the saved ones when none are given, using the
+/// browser a scrape with `options` would use.
+#[tauri::command]
+pub async fn test_credentials(
+    app: AppHandle,
+    username: Option<String>,
+    password: Option<String>,
+    options: Option<ScrapOptions>,
+) -> Result<(), String> {
+    let Credentials { username, password } = match (username, password) {
+        (Some(username), Some(password)) => Credentials { username, password },
+        _ => saved_credentials(&app).await?,
+    };
+    let options = with_app_paths(&app, options.unwrap_or_default());
+    let _browser = lock_browser(&app).await;
+    test_login(&app, &username, &password, options)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn forget_credentials(app: AppHandle) -> Result<(), String> {
+    let dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    tokio::task::spawn_blocking(move || credentials::forget_credentials(&dir))
+        .await
+        .map_err(|e| e.to_string())??;
+    log_progress("Saved credentials removed", LogLevel::Info, &app, true);
+    Ok(())
+}
+
+/// Returns the username of the saved credentials, to show who is remembered.
+#[tauri::command]
+pub async fn get_saved_username(app: AppHandle) -> Result<Option<String>, ()> {
+    Ok(saved_credentials(&app).await.ok().map(|c| c.username))
+}
+
+/// Signs out of in4SIT by deleting the persistent browser profile.
+#[tauri::command]
+pub async fn clear_browser_session(app: AppHandle) -> Result<(), String> {
+    let dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    let _browser = lock_browser(&app).await;
+    clear_browser_profile(&dir).await?;
+    log_progress("Browser session cleared", LogLevel::Info, &app, true);
+    Ok(())
+}
+
+/// Reports which browser a scrape would use and its version, `browser_path` being the
+/// executable set in settings.
+#[tauri::command]
+pub async fn check_browser(browser_path: Option<String>) -> Result<BrowserCheck, String> {
+    let configured = browser_path.filter(|p| !p.is_empty()).map(PathBuf::from);
+    // reading the version runs the browser and waits for it to exit
+    tokio::task::spawn_blocking(move || browser::check_browser(configured.as_deref()))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Downloads the bundled Chromium ahead of the first scrape that needs it.
+#[tauri::command]
+pub async fn install_bundled_chromium(app: AppHandle) -> Result<String, String> {
+    #[cfg(feature = "bundled-chromium")]
+    {
+        let dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+        let executable =
+            crate::fetcher::ensure_bundled_chromium(&bundled_chromium_path(&dir), &app).await?;
+        Ok(executable.display().to_string())
+    }
+    #[cfg(not(feature = "bundled-chromium"))]
+    {
+        let _ = app;
+        Err("This build cannot download Chromium, install Chrome or Chromium instead".to_string())
+    }
+}
+
+#[tauri::command]
+pub async fn get_saved_timetable(
+    state: State<'_, Mutex<AppState>>,
+) -> Result<Option<ScrapeMetadata>, ()> {
+    let state_v = state.lock().await;
+    Ok(state_v.last_scrape.clone())
+}
+
+#[tauri::command]
+pub async fn clear_saved_timetable(
+    app: AppHandle,
+    state: State<'_, Mutex<AppState>>,
+) -> Result<(), String> {
+    let dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    clear_last_scrape(&dir).await?;
+    let mut state_v = state.lock().await;
+    state_v.scrapped_info = vec![];
+    state_v.last_scrape = None;
+    Ok(())
+}
 
 #[tauri::command]
 pub async fn get_installed_version(state: State<'_, Mutex<AppState>>,) -> Result<String, ()> {
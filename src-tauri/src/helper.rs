@@ -1,8 +1,47 @@
-use crate::models::LogEvent;
-use chrono::{DateTime, Local, NaiveDateTime};
+use crate::models::{CourseInfo, LogEvent, TimeTableEntry};
+use crate::progress::ProgressSink;
+use chrono::{DateTime, FixedOffset, Local, NaiveDateTime};
+use chrono_tz::Tz;
+use icalendar::CalendarDateTime;
 use scraper::ElementRef;
-use tauri::{AppHandle, Emitter};
+use serde::{Deserialize, Deserializer};
 
+/// Times shown on in4SIT are always Singapore time, regardless of where the app runs.
+pub const PORTAL_TZ: Tz = chrono_tz::Asia::Singapore;
+
+// RFC 7986 only allows CSS3 colour names in COLOR, hex values are not valid there
+#[rustfmt::skip]
+const CSS3_COLOR_NAMES: [&str; 147] = [
+    "aliceblue", "antiquewhite", "aqua", "aquamarine", "azure", "beige", "bisque", "black",
+    "blanchedalmond", "blue", "blueviolet", "brown", "burlywood", "cadetblue", "chartreuse",
+    "chocolate", "coral", "cornflowerblue", "cornsilk", "crimson", "cyan", "darkblue", "darkcyan",
+    "darkgoldenrod", "darkgray", "darkgreen", "darkgrey", "darkkhaki", "darkmagenta",
+    "darkolivegreen", "darkorange", "darkorchid", "darkred", "darksalmon", "darkseagreen",
+    "darkslateblue", "darkslategray", "darkslategrey", "darkturquoise", "darkviolet", "deeppink",
+    "deepskyblue", "dimgray", "dimgrey", "dodgerblue", "firebrick", "floralwhite", "forestgreen",
+    "fuchsia", "gainsboro", "ghostwhite", "gold", "goldenrod", "gray", "green", "greenyellow",
+    "grey", "honeydew", "hotpink", "indianred", "indigo", "ivory", "khaki", "lavender",
+    "lavenderblush", "lawngreen", "lemonchiffon", "lightblue", "lightcoral", "lightcyan",
+    "lightgoldenrodyellow", "lightgray", "lightgreen", "lightgrey", "lightpink", "lightsalmon",
+    "lightseagreen", "lightskyblue", "lightslategray", "lightslategrey", "lightsteelblue",
+    "lightyellow", "lime", "limegreen", "linen", "magenta", "maroon", "mediumaquamarine",
+    "mediumblue", "mediumorchid", "mediumpurple", "mediumseagreen", "mediumslateblue",
+    "mediumspringgreen", "mediumturquoise", "mediumvioletred", "midnightblue", "mintcream",
+    "mistyrose", "moccasin", "navajowhite", "navy", "oldlace", "olive", "olivedrab", "orange",
+    "orangered", "orchid", "palegoldenrod", "palegreen", "paleturquoise", "palevioletred",
+    "papayawhip", "peachpuff", "peru", "pink", "plum", "powderblue", "purple", "red", "rosybrown",
+    "royalblue", "saddlebrown", "salmon", "sandybrown", "seagreen", "seashell", "sienna", "silver",
+    "skyblue", "slateblue", "slategray", "slategrey", "snow", "springgreen", "steelblue", "tan",
+    "teal", "thistle", "tomato", "turquoise", "violet", "wheat", "white", "whitesmoke", "yellow",
+    "yellowgreen",
+];
+
+/// Whether `color` is one of the CSS3 colour names an ICS COLOR property may hold.
+pub fn is_css3_color_name(color: &str) -> bool {
+    CSS3_COLOR_NAMES.contains(&color.to_ascii_lowercase().as_str())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum LogLevel {
     Debug,
     Info,
@@ -10,7 +49,7 @@ pub enum LogLevel {
     Error,
 }
 
-pub fn log_to_front(msg: &str, level: LogLevel, app: &AppHandle, with_ts: bool) {
+pub fn log_progress(msg: &str, level: LogLevel, sink: &dyn ProgressSink, with_ts: bool) {
     let msg = if with_ts {
         let now = Local::now();
         let now_fmt = now.format("%d/%m %H:%M:%S").to_string();
@@ -36,7 +75,47 @@ pub fn log_to_front(msg: &str, level: LogLevel, app: &AppHandle, with_ts: bool)
             level: 3,
         },
     };
-    app.emit("logs", ev).unwrap()
+    sink.report(ev)
+}
+
+/// Fills in the placeholders documented on `ExportOptions` for one table entry.
+pub fn render_template(template: &str, course: &CourseInfo, entry: &TimeTableEntry) -> String {
+    let (course_code, title) = course
+        .course_name
+        .split_once(" - ")
+        .unwrap_or((&course.course_name, ""));
+    // timetables saved before the name was parsed only have `course_name`
+    let course_code = match (&course.subject, &course.catalog_number) {
+        (Some(subject), Some(number)) => format!("{}{}", subject, number),
+        _ => course_code.trim().to_string(),
+    };
+    let title = course.title.as_deref().unwrap_or(title.trim());
+    template
+        .replace("{course}", &course.course_name)
+        .replace("{course_code}", &course_code)
+        .replace("{title}", title)
+        .replace("{section}", &entry.class_section)
+        .replace("{type}", entry.entry_type.label())
+        .replace("{location}", &entry.location)
+        .replace("{instructors}", &entry.instructors.join(", "))
+        .replace("{instructor_lines}", &entry.instructors.join("\n"))
+}
+
+/// Formats a portal time as a `TZID=Asia/Singapore` qualified calendar time.
+pub fn to_calendar_dt(dt: &DateTime<Tz>) -> CalendarDateTime {
+    CalendarDateTime::WithTimezone {
+        date_time: dt.naive_local(),
+        tzid: PORTAL_TZ.name().to_string(),
+    }
+}
+
+/// chrono can only deserialize fixed offsets, times are read back as RFC 3339 and moved into
+/// the portal time zone.
+pub fn deserialize_portal_dt<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<DateTime<Tz>, D::Error> {
+    let dt = DateTime::<FixedOffset>::deserialize(deserializer)?;
+    Ok(dt.with_timezone(&PORTAL_TZ))
 }
 
 pub fn get_inner_text_from_element(ele: &ElementRef) -> String {
@@ -45,7 +124,7 @@ pub fn get_inner_text_from_element(ele: &ElementRef) -> String {
 
 pub fn try_parse_string_to_start_end_dt(
     input: &str,
-) -> Result<(DateTime<Local>, DateTime<Local>), String> {
+) -> Result<(DateTime<Tz>, DateTime<Tz>), String> {
     let parts: Vec<&str> = input.split_ascii_whitespace().collect();
     if parts.len() == 5 {
         let date_part = parts[4];
@@ -57,7 +136,7 @@ pub fn try_parse_string_to_start_end_dt(
         let start_dt = NaiveDateTime::parse_from_str(&start_datetime_str, dt_fmt).map_err(|e| {
             format!(
                 "Fail to parse start dt, error: {}, value: {}, fmt: {}",
-                e.to_string(),
+                e,
                 start_datetime_str,
                 dt_fmt
             )
@@ -65,13 +144,13 @@ pub fn try_parse_string_to_start_end_dt(
         let end_dt = NaiveDateTime::parse_from_str(&end_datetime_str, dt_fmt).map_err(|e| {
             format!(
                 "Fail to parse end dt, error: {}, value: {}, fmt: {}",
-                e.to_string(),
+                e,
                 end_datetime_str,
                 dt_fmt
             )
         })?;
-        let start_dt = start_dt.and_local_timezone(Local).unwrap();
-        let end_dt = end_dt.and_local_timezone(Local).unwrap();
+        let start_dt = start_dt.and_local_timezone(PORTAL_TZ).unwrap();
+        let end_dt = end_dt.and_local_timezone(PORTAL_TZ).unwrap();
         Ok((start_dt, end_dt))
     } else {
         Err(format!(
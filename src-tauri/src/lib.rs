@@ -1,16 +1,45 @@
-use crate::handlers::{export_to_ics, get_installed_version, handle_credentials, is_update_available};
-use crate::models::CourseInfo;
+use crate::handlers::{
+    check_browser, clear_browser_session, clear_saved_timetable, diff_snapshots, export_timetable,
+    export_to_ics, forget_credentials, get_available_terms, get_installed_version,
+    get_saved_timetable, get_saved_username, get_scheduled_rescrape, get_subscription_url,
+    handle_credentials, import_html, install_bundled_chromium, is_update_available,
+    restore_background_tasks, save_credentials, start_scheduled_rescrape,
+    start_subscription_server, stop_scheduled_rescrape, stop_subscription_server, test_credentials,
+};
+use crate::models::{CourseInfo, ScrapeMetadata};
+use crate::scheduler::RescrapeSchedule;
+use crate::storage::load_last_scrape;
+use crate::subscription::SubscriptionServer;
+use std::sync::Arc;
 use tauri::Manager;
 use tokio::sync::Mutex;
 
+pub mod browser;
+pub mod credentials;
+pub mod diff;
+pub mod export;
+#[cfg(feature = "bundled-chromium")]
+pub mod fetcher;
 mod handlers;
-mod helper;
-mod models;
-mod scrap;
+pub mod helper;
+pub mod models;
+pub mod progress;
+pub mod recurrence;
+mod scheduler;
+pub mod scrap;
+pub mod storage;
+pub mod subscription;
 mod updater;
+pub mod wait;
 
 pub struct AppState {
     pub scrapped_info: Vec<CourseInfo>,
+    pub last_scrape: Option<ScrapeMetadata>,
+    pub subscription_server: Option<SubscriptionServer>,
+    pub rescrape_schedule: Option<RescrapeSchedule>,
+    /// Held for as long as a browser runs, scrapes share one persistent profile directory
+    /// and Chromium refuses to start on a profile that is already in use.
+    pub browser_lock: Arc<Mutex<()>>,
     pub version_string: String,
 }
 
@@ -18,6 +47,10 @@ impl AppState {
     fn new() -> Self {
         AppState {
             scrapped_info: vec![],
+            last_scrape: None,
+            subscription_server: None,
+            rescrape_schedule: None,
+            browser_lock: Arc::new(Mutex::new(())),
             version_string: "1.0.0".to_string(),
         }
     }
@@ -25,15 +58,21 @@ impl AppState {
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    let app_state = AppState::new();
+    let mut app_state = AppState::new();
     tauri::Builder::default()
         .setup(|app| {
+            if let Some(saved) = app.path().app_data_dir().ok().and_then(|d| load_last_scrape(&d)) {
+                app_state.scrapped_info = saved.courses;
+                app_state.last_scrape = Some(saved.metadata);
+            }
             app.manage(Mutex::new(app_state));
+            tauri::async_runtime::spawn(restore_background_tasks(app.handle().clone()));
             Ok(())
         })
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_dialog::init())
-        .invoke_handler(tauri::generate_handler![handle_credentials, export_to_ics, get_installed_version, is_update_available])
+        .plugin(tauri_plugin_notification::init())
+        .invoke_handler(tauri::generate_handler![handle_credentials, get_available_terms, import_html, export_to_ics, export_timetable, get_saved_timetable, clear_saved_timetable, diff_snapshots, start_subscription_server, stop_subscription_server, get_subscription_url, start_scheduled_rescrape, stop_scheduled_rescrape, get_scheduled_rescrape, save_credentials, test_credentials, forget_credentials, get_saved_username, clear_browser_session, check_browser, install_bundled_chromium, get_installed_version, is_update_available])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
@@ -1,6 +1,19 @@
-use chrono::{DateTime, Local};
-use icalendar::{Component, Event, EventLike};
+use crate::diff::EntryChange;
+use crate::helper::{
+    deserialize_portal_dt, is_css3_color_name, render_template, to_calendar_dt, PORTAL_TZ,
+};
+use crate::recurrence::{compress_weekly, EntrySeries};
+use chrono::{DateTime, Duration, Utc};
+use chrono_tz::Tz;
+use icalendar::{Alarm, Component, Event, EventLike, Property, Trigger};
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use uuid::Uuid;
+
+// Namespace for the name-based UIDs of exported events, never change this or
+// previously imported events will be duplicated on the next import.
+const EVENT_UID_NAMESPACE: Uuid = Uuid::from_u128(0x5d1c_6f4e_2b8a_4c3e_9a71_0e4f_3b2d_8c15);
 
 // level 0 = debug
 // level 1 = info
@@ -12,44 +25,277 @@ pub struct LogEvent {
     pub level: u8,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct ScrapOptions {
+    pub debug_mode: bool,
+    // keep the browser profile under the app data dir so the SSO session survives
+    #[serde(default)]
+    pub persist_session: bool,
+    // resolved by the backend from `persist_session`, a fresh profile is used when unset
+    #[serde(skip)]
+    pub user_data_dir: Option<PathBuf>,
+    // let the user sign in in a visible browser, for MFA and captchas
+    #[serde(default)]
+    pub manual_login: bool,
+    #[serde(default)]
+    pub manual_login_timeout_secs: Option<u64>,
+    // how long to wait for each page or element while scraping, 30 seconds by default
+    #[serde(default)]
+    pub wait_timeout_secs: Option<u64>,
+    // browser executable chosen in settings, a known install is searched for when unset
+    #[serde(default)]
+    pub browser_path: Option<PathBuf>,
+    // where the `bundled-chromium` feature keeps its download, resolved by the backend
+    #[serde(skip)]
+    pub bundled_browser_dir: Option<PathBuf>,
+    // term to scrape by name, e.g. "Trimester 1 2025-26", the one in4SIT shows when unset
+    #[serde(default)]
+    pub term: Option<String>,
+    // several terms scraped in one session, e.g. a whole academic year, `term` is used when empty
+    #[serde(default)]
+    pub terms: Vec<String>,
+}
+
+// Templates accept {course}, {course_code}, {title}, {section}, {type}, {location},
+// {instructors} (comma separated) and {instructor_lines} (one per line)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct ExportOptions {
+    pub compress_recurring: bool,
+    pub summary_template: String,
+    pub description_template: String,
+    pub alarm_minutes_before: Option<u32>,
+    // keyed by entry type label, e.g. "Lecture"
+    pub entry_type_styles: HashMap<String, EntryTypeStyle>,
+    // only write what changed since the previous ICS export to the same path
+    pub incremental: bool,
+    // every class is scraped, dropped and waitlisted ones are left out here instead
     pub filter_dropped: bool,
     pub filter_waitlisted: bool,
-    pub debug_mode: bool,
 }
 
-#[derive(Serialize, Debug, Clone)]
+impl Default for ExportOptions {
+    fn default() -> Self {
+        ExportOptions {
+            compress_recurring: false,
+            summary_template: "{course} - {section} - {type}".to_string(),
+            description_template: "Profs\n{instructor_lines}".to_string(),
+            alarm_minutes_before: None,
+            entry_type_styles: HashMap::new(),
+            incremental: false,
+            filter_dropped: false,
+            filter_waitlisted: false,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct EntryTypeStyle {
+    // any CSS colour for the HTML export, the ICS export only keeps CSS3 names like "teal"
+    pub color: Option<String>,
+    pub category: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct SubscriptionOptions {
+    // 0 picks a free port
+    pub port: u16,
+    // listen on every interface instead of localhost only, for phones on the same network
+    pub allow_lan: bool,
+}
+
+impl Default for SubscriptionOptions {
+    fn default() -> Self {
+        SubscriptionOptions {
+            port: 8765,
+            allow_lan: false,
+        }
+    }
+}
+
+/// The subscription server as last started, kept in the app data directory so the URL calendars
+/// subscribed to keeps working after a restart.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SavedSubscription {
+    pub token: String,
+    // the port actually bound, so a free port picked with `port: 0` is reused too
+    pub port: u16,
+    pub allow_lan: bool,
+    pub export_options: ExportOptions,
+    // whether the server was still running when the app quit
+    pub running: bool,
+}
+
+/// The scheduled re-scrape, kept in the app data directory so it is resumed on the next start.
+/// Credentials given for the schedule are never written here, a resumed schedule uses the
+/// saved ones.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SavedSchedule {
+    pub interval_hours: u32,
+}
+
+/// A term the student can pick on the in4SIT term selection page.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Term {
+    // row of the term on the selection page
+    pub index: usize,
+    // e.g. "Trimester 2 2024-25", as reported in `ScrapResult::term`
+    pub name: String,
+    pub career: Option<String>,
+    pub institution: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub enum EntryType {
     Quiz,
     Tutorial,
     Lab,
     Lecture,
     Workshop,
-    Unknown,
+    Seminar,
+    Studio,
+    Project,
+    Practical,
+    Exam,
+    // online or asynchronous learning
+    Online,
+    // a component not listed above, as the portal labels it
+    Other(String),
 }
 
-#[derive(Serialize, Debug, Clone)]
+/// The status column of the course header table.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum EnrollmentStatus {
+    Enrolled,
+    Dropped,
+    Waitlisted,
+    Other(String),
+}
+
+impl EnrollmentStatus {
+    pub fn from_portal(text: &str) -> Self {
+        match text.trim() {
+            "Enrolled" => EnrollmentStatus::Enrolled,
+            "Dropped" => EnrollmentStatus::Dropped,
+            // the study list says "Waiting" for waitlisted classes
+            "Waiting" | "Waitlisted" => EnrollmentStatus::Waitlisted,
+            other => EnrollmentStatus::Other(other.to_string()),
+        }
+    }
+
+    pub fn label(&self) -> &str {
+        match self {
+            EnrollmentStatus::Enrolled => "Enrolled",
+            EnrollmentStatus::Dropped => "Dropped",
+            EnrollmentStatus::Waitlisted => "Waitlisted",
+            EnrollmentStatus::Other(text) => text,
+        }
+    }
+}
+
+impl EntryType {
+    pub fn label(&self) -> &str {
+        match self {
+            EntryType::Quiz => "Quiz",
+            EntryType::Tutorial => "Tutorial",
+            EntryType::Lab => "Lab",
+            EntryType::Lecture => "Lecture",
+            EntryType::Workshop => "Workshop",
+            EntryType::Seminar => "Seminar",
+            EntryType::Studio => "Studio",
+            EntryType::Project => "Project",
+            EntryType::Practical => "Practical",
+            EntryType::Exam => "Exam",
+            EntryType::Online => "Online",
+            EntryType::Other(label) => label,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct TimeTableEntry {
     pub entry_type: EntryType,
+    // the component column as the portal shows it, e.g. "Laboratory"
+    #[serde(default)]
+    pub component: String,
+    // in4SIT class number, shared by every meeting of the class
+    #[serde(default)]
+    pub class_number: Option<String>,
     pub class_section: String,
     pub location: String,
     pub instructors: Vec<String>,
-    pub start_datetime: DateTime<Local>,
-    pub end_datetime: DateTime<Local>,
+    #[serde(deserialize_with = "deserialize_portal_dt")]
+    pub start_datetime: DateTime<Tz>,
+    #[serde(deserialize_with = "deserialize_portal_dt")]
+    pub end_datetime: DateTime<Tz>,
 }
 
-#[derive(Serialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct CourseInfo {
+    // as shown on the study list, e.g. "CSC1107 - Operating Systems"
     pub course_name: String,
+    // term of the study list the course was on, None for timetables saved before terms were kept
+    #[serde(default)]
+    pub term: Option<String>,
+    // the fields below are parsed from `course_name` and the course header table, None when
+    // the portal left them out or the timetable was saved before they were parsed
+    #[serde(default)]
+    pub subject: Option<String>,
+    #[serde(default)]
+    pub catalog_number: Option<String>,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub units: Option<f32>,
+    #[serde(default)]
+    pub grading_basis: Option<String>,
+    #[serde(default)]
+    pub enrollment_status: Option<EnrollmentStatus>,
     pub table_entries: Vec<TimeTableEntry>,
 }
 
 #[derive(Serialize, Debug, Clone)]
 pub struct ScrapResult {
+    pub term: Option<String>,
     pub skipped_unknown_course_count: u8,
     pub skipped_table_entry_count: u8,
     pub errors_present: bool,
+    // compared against the previous timetable in the app, empty on the first scrape
+    pub changes: Vec<EntryChange>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ScrapeMetadata {
+    pub scraped_at: DateTime<Utc>,
+    pub term: Option<String>,
+    // None when the timetable was imported from a saved page
+    pub options: Option<ScrapOptions>,
+}
+
+/// The last successful scrape, kept in the app data directory between runs.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SavedTimetable {
+    #[serde(flatten)]
+    pub metadata: ScrapeMetadata,
+    pub courses: Vec<CourseInfo>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ExportedEvent {
+    pub sequence: u32,
+    // the event as written, without DTSTAMP, SEQUENCE and UID lines
+    pub content: String,
+}
+
+/// Recorded next to an exported .ics file so the next export to it can be incremental.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ExportSnapshot {
+    pub exported_at: DateTime<Utc>,
+    pub courses: Vec<CourseInfo>,
+    // keyed by event UID
+    pub events: BTreeMap<String, ExportedEvent>,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -57,22 +303,95 @@ pub struct GithubLatestReleaseRes {
     pub tag_name: String,
 }
 
-impl Into<Vec<Event>> for CourseInfo {
-    fn into(self) -> Vec<Event> {
-        let name = self.course_name.clone();
-        self.table_entries
+impl TimeTableEntry {
+    /// Derives the event UID from the course, section, entry type and start time, so exporting
+    /// the same timetable again produces the same UIDs. Only the label of the entry type goes
+    /// into it, its Debug form changes whenever the enum does.
+    pub fn uid(&self, course_name: &str) -> String {
+        let key = format!(
+            "{}|{}|{}|{}",
+            course_name,
+            self.class_section,
+            self.entry_type.label(),
+            self.start_datetime.to_utc().format("%Y%m%dT%H%M%SZ")
+        );
+        format!(
+            "{}@export-sit-timetable",
+            Uuid::new_v5(&EVENT_UID_NAMESPACE, key.as_bytes())
+        )
+    }
+}
+
+impl ExportOptions {
+    /// Whether the course passes the dropped and waitlisted filters.
+    pub fn includes(&self, course: &CourseInfo) -> bool {
+        match course.enrollment_status {
+            Some(EnrollmentStatus::Dropped) => !self.filter_dropped,
+            Some(EnrollmentStatus::Waitlisted) => !self.filter_waitlisted,
+            _ => true,
+        }
+    }
+}
+
+impl CourseInfo {
+    /// Converts every table entry into an event, or every weekly series into a recurring event
+    /// when `compress_recurring` is set. All events of an export share the same DTSTAMP. Every
+    /// event starts at SEQUENCE 0, `export_ics` raises it when the event changed since the
+    /// previous export.
+    pub fn to_events(&self, exported_at: DateTime<Utc>, options: &ExportOptions) -> Vec<Event> {
+        let name = &self.course_name;
+        let series = if options.compress_recurring {
+            compress_weekly(&self.table_entries)
+        } else {
+            self.table_entries
+                .iter()
+                .cloned()
+                .map(EntrySeries::single)
+                .collect()
+        };
+        series
             .into_iter()
-            .map(|e| {
-                let summary = format!("{} - {} - {:?}", name, e.class_section, e.entry_type);
-                let description_header = "Profs\n";
-                let description = format!("{}{}", description_header, e.instructors.join("\n"));
-                Event::new()
+            .map(|series| {
+                let e = &series.first;
+                let summary = render_template(&options.summary_template, self, e);
+                let description = render_template(&options.description_template, self, e);
+                let mut event = Event::new();
+                event
+                    .uid(&e.uid(name))
+                    .timestamp(exported_at)
+                    .sequence(0)
                     .summary(&summary)
-                    .starts(e.start_datetime.to_utc())
-                    .ends(e.end_datetime.to_utc())
+                    .starts(to_calendar_dt(&e.start_datetime))
+                    .ends(to_calendar_dt(&e.end_datetime))
                     .location(e.location.as_str())
-                    .description(&description)
-                    .done()
+                    .description(&description);
+                if let Some(rrule) = series.rrule() {
+                    event.add_property("RRULE", rrule);
+                    for skipped in &series.skipped {
+                        let exdate = skipped.format("%Y%m%dT%H%M%S").to_string();
+                        event.append_multi_property(
+                            Property::new("EXDATE", &exdate)
+                                .add_parameter("TZID", PORTAL_TZ.name())
+                                .done(),
+                        );
+                    }
+                }
+                if let Some(minutes) = options.alarm_minutes_before {
+                    event.alarm(Alarm::display(
+                        &summary,
+                        Trigger::before_start(Duration::minutes(minutes.into())),
+                    ));
+                }
+                if let Some(style) = options.entry_type_styles.get(e.entry_type.label()) {
+                    let color = style.color.as_deref().filter(|c| is_css3_color_name(c));
+                    if let Some(color) = color {
+                        event.add_property("COLOR", color);
+                    }
+                    if let Some(category) = &style.category {
+                        event.add_property("CATEGORIES", category);
+                    }
+                }
+                event.done()
             })
             .collect()
     }
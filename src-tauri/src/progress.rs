@@ -0,0 +1,50 @@
+use crate::models::LogEvent;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter};
+
+/// Receives the log lines produced while scraping and parsing a timetable.
+pub trait ProgressSink: Send + Sync {
+    fn report(&self, event: LogEvent);
+}
+
+/// Forwards log lines to the frontend as `logs` events.
+impl ProgressSink for AppHandle {
+    fn report(&self, event: LogEvent) {
+        self.emit("logs", event).unwrap()
+    }
+}
+
+/// Prints log lines to stderr, used when running without a Tauri app.
+pub struct StderrSink;
+
+impl ProgressSink for StderrSink {
+    fn report(&self, event: LogEvent) {
+        eprintln!("{}", event.message);
+    }
+}
+
+/// Keeps every log line in memory so callers can inspect them afterwards.
+#[derive(Default)]
+pub struct CollectingSink {
+    events: Mutex<Vec<LogEvent>>,
+}
+
+impl CollectingSink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn events(&self) -> Vec<LogEvent> {
+        self.events.lock().unwrap().clone()
+    }
+
+    pub fn messages(&self) -> Vec<String> {
+        self.events().into_iter().map(|e| e.message).collect()
+    }
+}
+
+impl ProgressSink for CollectingSink {
+    fn report(&self, event: LogEvent) {
+        self.events.lock().unwrap().push(event);
+    }
+}
@@ -0,0 +1,98 @@
+use crate::models::TimeTableEntry;
+use chrono::{DateTime, Duration};
+use chrono_tz::Tz;
+
+/// A meeting that repeats every `interval_weeks` weeks, `count` times in total including the
+/// weeks in `skipped` where it does not take place (e.g. recess week).
+#[derive(Debug, Clone)]
+pub struct EntrySeries {
+    pub first: TimeTableEntry,
+    pub interval_weeks: i64,
+    pub count: i64,
+    pub skipped: Vec<DateTime<Tz>>,
+}
+
+impl EntrySeries {
+    pub fn single(entry: TimeTableEntry) -> Self {
+        EntrySeries {
+            first: entry,
+            interval_weeks: 1,
+            count: 1,
+            skipped: vec![],
+        }
+    }
+
+    pub fn is_recurring(&self) -> bool {
+        self.count > 1
+    }
+
+    pub fn rrule(&self) -> Option<String> {
+        if !self.is_recurring() {
+            return None;
+        }
+        Some(format!(
+            "FREQ=WEEKLY;INTERVAL={};COUNT={}",
+            self.interval_weeks, self.count
+        ))
+    }
+}
+
+/// Meetings of the same section, type, venue and instructors on the same weekday and time
+/// belong to the same series.
+fn same_series(a: &TimeTableEntry, b: &TimeTableEntry) -> bool {
+    a.class_section == b.class_section
+        && a.entry_type == b.entry_type
+        && a.location == b.location
+        && a.instructors == b.instructors
+        && a.start_datetime.format("%a %H:%M").to_string()
+            == b.start_datetime.format("%a %H:%M").to_string()
+        && a.end_datetime - a.start_datetime == b.end_datetime - b.start_datetime
+}
+
+fn gcd(a: i64, b: i64) -> i64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+fn build_series(mut group: Vec<&TimeTableEntry>) -> EntrySeries {
+    group.sort_by_key(|e| e.start_datetime);
+    let first = group[0];
+    let first_date = first.start_datetime.date_naive();
+    let mut week_offsets = group
+        .iter()
+        .map(|e| (e.start_datetime.date_naive() - first_date).num_days() / 7)
+        .collect::<Vec<_>>();
+    week_offsets.dedup();
+    let interval_weeks = week_offsets.iter().fold(0, |acc, &w| gcd(acc, w));
+    if interval_weeks == 0 {
+        return EntrySeries::single(first.clone());
+    }
+    let count = week_offsets.last().unwrap() / interval_weeks + 1;
+    let skipped = (0..count)
+        .map(|i| i * interval_weeks)
+        .filter(|w| !week_offsets.contains(w))
+        .map(|w| first.start_datetime + Duration::weeks(w))
+        .collect();
+    EntrySeries {
+        first: first.clone(),
+        interval_weeks,
+        count,
+        skipped,
+    }
+}
+
+/// Groups weekly repeating meetings into series, entries that do not repeat become a series
+/// of one. Series are returned in the order their first meeting appears in `entries`.
+pub fn compress_weekly(entries: &[TimeTableEntry]) -> Vec<EntrySeries> {
+    let mut groups: Vec<Vec<&TimeTableEntry>> = vec![];
+    for entry in entries {
+        match groups.iter_mut().find(|g| same_series(g[0], entry)) {
+            Some(group) => group.push(entry),
+            None => groups.push(vec![entry]),
+        }
+    }
+    groups.into_iter().map(build_series).collect()
+}
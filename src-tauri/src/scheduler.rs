@@ -0,0 +1,138 @@
+use crate::credentials::Credentials;
+use crate::diff::{ChangeKind, EntryChange};
+use crate::handlers::{lock_browser, saved_credentials, store_scrape, with_app_paths};
+use crate::helper::{log_progress, LogLevel};
+use crate::scrap::{extract_timetables, start_scrap};
+use crate::AppState;
+use chrono::{TimeDelta, Utc};
+use std::time::Duration;
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_notification::NotificationExt;
+use tokio::sync::Mutex;
+
+pub const DEFAULT_INTERVAL_HOURS: u32 = 24;
+
+/// Emitted with every change of a background re-scrape, when a class moved, changed venue,
+/// was added or was removed.
+pub const TIMETABLE_CHANGED_EVENT: &str = "timetable-changed";
+
+/// Periodic re-scrape running in the background until [`RescrapeSchedule::stop`].
+pub struct RescrapeSchedule {
+    pub interval_hours: u32,
+    task: JoinHandle<()>,
+}
+
+impl RescrapeSchedule {
+    /// Given credentials only live in the task for as long as the schedule runs. Without them
+    /// the saved credentials are read before every re-scrape, so forgetting them also stops
+    /// the re-scrapes.
+    pub fn start(app: AppHandle, credentials: Option<Credentials>, interval_hours: u32) -> Self {
+        let interval = Duration::from_secs(u64::from(interval_hours) * 60 * 60);
+        let task = tauri::async_runtime::spawn(async move {
+            // the schedule only runs while the app is open, a re-scrape that fell due while it
+            // was closed happens right away instead of a full interval after the start
+            let state = app.state::<Mutex<AppState>>();
+            let scraped_at = state
+                .lock()
+                .await
+                .last_scrape
+                .as_ref()
+                .map(|m| m.scraped_at);
+            let mut wait = match scraped_at {
+                Some(at) => (at + TimeDelta::hours(i64::from(interval_hours)) - Utc::now())
+                    .to_std()
+                    .unwrap_or_default(),
+                None => interval,
+            };
+            loop {
+                tokio::time::sleep(wait).await;
+                wait = interval;
+                let credentials = match &credentials {
+                    Some(c) => Some(c.clone()),
+                    None => saved_credentials(&app).await.ok(),
+                };
+                match credentials {
+                    Some(c) => rescrape(&app, &c.username, &c.password).await,
+                    None => log_progress(
+                        "Skipping scheduled re-scrape, no saved credentials",
+                        LogLevel::Warn,
+                        &app,
+                        true,
+                    ),
+                }
+            }
+        });
+        RescrapeSchedule {
+            interval_hours,
+            task,
+        }
+    }
+
+    pub fn stop(self) {
+        self.task.abort();
+    }
+}
+
+async fn rescrape(app: &AppHandle, username: &str, password: &str) {
+    log_progress("Starting scheduled re-scrape", LogLevel::Info, app, true);
+    let state = app.state::<Mutex<AppState>>();
+    // reuse the options of the last scrape, without a window and without waiting for a
+    // manual sign in, nobody is there to watch
+    let mut options = state
+        .lock()
+        .await
+        .last_scrape
+        .as_ref()
+        .and_then(|m| m.options.clone())
+        .unwrap_or_default();
+    options.debug_mode = false;
+    options.manual_login = false;
+    let options = with_app_paths(app, options);
+
+    let browser = lock_browser(app).await;
+    let scraped = match start_scrap(app, username, password, options.clone()).await {
+        Ok(pages) => extract_timetables(pages, app),
+        Err(e) => Err(e),
+    };
+    drop(browser);
+    let (r, courses_info) = match scraped {
+        Ok(scraped) => scraped,
+        Err(e) => {
+            let msg = format!("Scheduled re-scrape failed: {}", e);
+            log_progress(&msg, LogLevel::Warn, app, true);
+            return;
+        }
+    };
+    let mut state_v = state.lock().await;
+    let changes = store_scrape(app, &mut state_v, courses_info, r.term, Some(options)).await;
+    drop(state_v);
+    notify_changes(app, &changes);
+}
+
+fn notify_changes(app: &AppHandle, changes: &[EntryChange]) {
+    // a new instructor alone does not change where the student has to be
+    if changes
+        .iter()
+        .all(|c| c.kind == ChangeKind::InstructorChanged)
+    {
+        return;
+    }
+    if let Err(e) = app.emit(TIMETABLE_CHANGED_EVENT, changes) {
+        log_progress(&e.to_string(), LogLevel::Warn, app, true);
+    }
+    let body = match changes.len() {
+        1 => changes[0].describe(),
+        n => format!("{}, and {} more changes", changes[0].describe(), n - 1),
+    };
+    let shown = app
+        .notification()
+        .builder()
+        .title("Your timetable changed")
+        .body(body)
+        .show();
+    if let Err(e) = shown {
+        let msg = format!("Could not show notification: {}", e);
+        log_progress(&msg, LogLevel::Warn, app, true);
+    }
+}
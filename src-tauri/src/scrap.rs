@@ -1,29 +1,56 @@
+use crate::browser::find_browser;
+#[cfg(feature = "bundled-chromium")]
+use crate::fetcher::ensure_bundled_chromium;
 use crate::helper::{
-    get_inner_text_from_element, log_to_front, try_parse_string_to_start_end_dt, LogLevel,
+    get_inner_text_from_element, log_progress, try_parse_string_to_start_end_dt, LogLevel,
 };
-use crate::models::{CourseInfo, EntryType, ScrapOptions, ScrapResult, TimeTableEntry};
+use crate::models::{
+    CourseInfo, EnrollmentStatus, EntryType, ScrapOptions, ScrapResult, Term, TimeTableEntry,
+};
+use crate::progress::ProgressSink;
+use crate::wait::{wait_for_selector, wait_until, NetworkMonitor, WaitOptions};
 use chromiumoxide::error::CdpError;
-use chromiumoxide::{Browser, BrowserConfig};
+use chromiumoxide::{Browser, BrowserConfig, Page};
 use chrono::Local;
 use futures::StreamExt;
 use scraper::{Html, Selector};
 use std::fmt;
-use std::time::Duration;
-use tauri::{AppHandle, Url};
-use tokio::time::sleep;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use url::Url;
 
 const CALENDER_LINK: &str = "https://in4sit.singaporetech.edu.sg/psc/CSSISSTD_4/EMPLOYEE/SA/c/SA_LEARNER_SERVICES.SSR_SSENRL_LIST.GBL?Page=SSR_SSENRL_LIST&Action=A";
 
+const PORTAL_HOST: &str = "in4sit.singaporetech.edu.sg";
+
+const DEFAULT_MANUAL_LOGIN_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
 const LANDING_PAGE: &str = "https://in4sit.singaporetech.edu.sg/psc/CSSISSTD/EMPLOYEE/SA/c/NUI_FRAMEWORK.PT_LANDINGPAGE.GBL";
 
+// ids with `$` in them are matched by attribute, a `$` would need escaping in an id selector
+const SHOW_DROPPED_CHECKBOX: &str = "#DERIVED_REGFRM1_SA_STUDYLIST_D";
+const SHOW_WAITLISTED_CHECKBOX: &str = "#DERIVED_REGFRM1_SA_STUDYLIST_W";
+const FILTER_BUTTON: &str = "[id='DERIVED_REGFRM1_SA_STUDYLIST_SHOW$14$']";
+
+// shown instead of the study list when the student is enrolled in more than one term
+const TERM_RADIO: &str = "[id^='SSR_DUMMY_RECV1$sels$']";
+const TERM_CONTINUE_BUTTON: &str = "#DERIVED_SSS_SCT_SSR_PB_GO";
+const CHANGE_TERM_BUTTON: &str = "#DERIVED_SSS_SCT_SSS_TERM_LINK";
+
+// how long the page has to stay quiet after filtering before it is read
+const NETWORK_IDLE: Duration = Duration::from_millis(500);
+
 #[derive(Debug, Clone)]
 pub enum ScrapError {
     BrowserError(String),
     NavigationError(String),
     JSException(String),
     LoginFailed,
+    Timeout(String),
     HtmlParseError(String),
     NetworkError,
+    FileError(String),
 }
 
 impl fmt::Display for ScrapError {
@@ -43,10 +70,14 @@ impl fmt::Display for ScrapError {
                 write!(f, "[{}] HTML Parsing Error: {}", now_fmt, msg)
             }
             ScrapError::LoginFailed => write!(f, "[{}] Login Failed", now_fmt),
+            ScrapError::Timeout(msg) => write!(f, "[{}] Timed out {}", now_fmt, msg),
+            ScrapError::FileError(msg) => write!(f, "[{}] File Error: {}", now_fmt, msg),
         }
     }
 }
 
+impl std::error::Error for ScrapError {}
+
 impl From<CdpError> for ScrapError {
     fn from(value: CdpError) -> Self {
         match value {
@@ -62,161 +93,621 @@ impl From<CdpError> for ScrapError {
                 -32000 => ScrapError::NavigationError("Selector not found".to_string()),
                 _ => ScrapError::BrowserError("Generic Browser error".to_string()),
             },
-            _ => {
-                println!("{:?}", value);
-                ScrapError::BrowserError("Generic Browser error".to_string())
-            }
+            // no sink here, the details go with the error that the caller logs
+            _ => ScrapError::BrowserError(format!("Generic Browser error: {:?}", value)),
         }
     }
 }
 
-pub async fn start_scrap(
-    app: &AppHandle,
-    username: &str,
-    password: &str,
-    options: ScrapOptions,
-) -> Result<String, ScrapError> {
-    let config = if options.debug_mode {
-        BrowserConfig::builder()
-            .arg("--lang=en-US")
-            .with_head()
-            .build()
-    } else {
-        BrowserConfig::builder().arg("--lang=en-US").build()
+fn no_browser_error(sink: &dyn ProgressSink, options: &ScrapOptions) -> ScrapError {
+    let msg = match &options.browser_path {
+        Some(path) => format!("No browser found at {}", path.display()),
+        None => "No Chrome, Chromium, Edge or Brave installation found, install one or \
+                 set the browser path in settings"
+            .to_string(),
+    };
+    log_progress(&msg, LogLevel::Error, sink, true);
+    ScrapError::BrowserError(msg)
+}
+
+/// Without a system browser the bundled Chromium is used, downloading it the first time.
+#[cfg(feature = "bundled-chromium")]
+async fn missing_browser(
+    sink: &dyn ProgressSink,
+    options: &ScrapOptions,
+) -> Result<PathBuf, ScrapError> {
+    let (None, Some(dir)) = (&options.browser_path, &options.bundled_browser_dir) else {
+        return Err(no_browser_error(sink, options));
+    };
+    log_progress(
+        "No browser installed, using the bundled Chromium",
+        LogLevel::Info,
+        sink,
+        true,
+    );
+    ensure_bundled_chromium(dir, sink).await.map_err(|e| {
+        let msg = format!("Could not download Chromium: {}", e);
+        log_progress(&msg, LogLevel::Error, sink, true);
+        ScrapError::BrowserError(msg)
+    })
+}
+
+#[cfg(not(feature = "bundled-chromium"))]
+async fn missing_browser(
+    sink: &dyn ProgressSink,
+    options: &ScrapOptions,
+) -> Result<PathBuf, ScrapError> {
+    Err(no_browser_error(sink, options))
+}
+
+async fn launch_browser(
+    sink: &dyn ProgressSink,
+    options: &ScrapOptions,
+) -> Result<Browser, ScrapError> {
+    let executable = match find_browser(options.browser_path.as_deref()) {
+        Some(path) => path,
+        None => missing_browser(sink, options).await?,
+    };
+    let mut builder = BrowserConfig::builder()
+        .chrome_executable(&executable)
+        .arg("--lang=en-US");
+    if options.debug_mode || options.manual_login {
+        builder = builder.with_head();
+    }
+    if let Some(dir) = &options.user_data_dir {
+        builder = builder.user_data_dir(dir);
     }
-    .map_err(|e| {
-        log_to_front(&e, LogLevel::Error, app, true);
+    let config = builder.build().map_err(|e| {
+        log_progress(&e, LogLevel::Error, sink, true);
         ScrapError::BrowserError(e)
     })?;
-    let (mut browser, mut handler) = Browser::launch(config).await?;
-    let _ = tokio::spawn(async move {
-        loop {
-            let _event = handler.next().await.unwrap();
+    let (browser, mut handler) = Browser::launch(config).await.map_err(|e| {
+        let msg = format!("Could not start {}: {}", executable.display(), e);
+        log_progress(&msg, LogLevel::Error, sink, true);
+        ScrapError::BrowserError(msg)
+    })?;
+    // the stream ends once the browser is closed
+    tokio::spawn(async move {
+        while let Some(event) = handler.next().await {
+            if event.is_err() {
+                break;
+            }
         }
     });
+    Ok(browser)
+}
+
+fn wait_options(options: &ScrapOptions) -> WaitOptions {
+    let mut wait = WaitOptions::default();
+    if let Some(secs) = options.wait_timeout_secs {
+        wait.timeout = Duration::from_secs(secs);
+    }
+    wait
+}
+
+fn is_portal_url(url: &str) -> bool {
+    Url::parse(url).is_ok_and(|u| u.host_str() == Some(PORTAL_HOST))
+}
+
+/// Waits for the landing page when the session is still valid or for the sign in form,
+/// returning whether the user is already logged in.
+async fn wait_for_landing_or_sign_in(page: &Page, wait: &WaitOptions) -> Result<bool, ScrapError> {
+    wait_until("the landing page or sign in form", wait, || async move {
+        if page.url().await?.as_deref() == Some(LANDING_PAGE) {
+            return Ok(Some(true));
+        }
+        Ok(page
+            .find_element("#userNameInput")
+            .await
+            .ok()
+            .map(|_| false))
+    })
+    .await
+}
+
+/// Waits for the redirect back to in4SIT, or for ADFS to show why signing in failed.
+async fn wait_for_sign_in_result(page: &Page, wait: &WaitOptions) -> Result<bool, ScrapError> {
+    wait_until("in4SIT after signing in", wait, || async move {
+        if page.url().await?.as_deref().is_some_and(is_portal_url) {
+            return Ok(Some(true));
+        }
+        let error = match page.find_element("#errorText").await {
+            Ok(element) => element.inner_text().await?.unwrap_or_default(),
+            Err(_) => String::new(),
+        };
+        Ok((!error.trim().is_empty()).then_some(false))
+    })
+    .await
+}
+
+/// Opens the landing page and signs in unless the session is still valid, leaving the page
+/// on in4SIT.
+async fn log_in(
+    browser: &Browser,
+    sink: &dyn ProgressSink,
+    username: &str,
+    password: &str,
+    wait: &WaitOptions,
+) -> Result<Page, ScrapError> {
     let page = browser.new_page(LANDING_PAGE).await?;
-    let page = page.wait_for_navigation().await?;
-    sleep(Duration::from_secs(1)).await;
-    if page.url().await? == Some(LANDING_PAGE.to_string()) {
-        log_to_front(
+    if wait_for_landing_or_sign_in(&page, wait).await? {
+        log_progress(
             "Website loaded, already logged in",
             LogLevel::Info,
-            app,
+            sink,
             true,
         );
     } else {
-        log_to_front("Website loaded, logging in", LogLevel::Info, app, true);
-        log_to_front("Entering email address", LogLevel::Info, app, true);
-        page.find_xpath("//*[@id=\"userNameInput\"]")
+        log_progress("Website loaded, logging in", LogLevel::Info, sink, true);
+        log_progress("Entering email address", LogLevel::Info, sink, true);
+        wait_for_selector(&page, "#userNameInput", wait)
             .await?
             .click()
             .await?
             .type_str(username)
             .await?;
-        log_to_front("Entering password", LogLevel::Info, app, true);
-        page.find_xpath("//*[@id=\"passwordInput\"]")
+        log_progress("Entering password", LogLevel::Info, sink, true);
+        wait_for_selector(&page, "#passwordInput", wait)
             .await?
             .click()
             .await?
             .type_str(password)
             .await?;
-        log_to_front("Submitting sign in form", LogLevel::Info, app, true);
-        page.find_xpath("//*[@id=\"submitButton\"]")
+        log_progress("Submitting sign in form", LogLevel::Info, sink, true);
+        wait_for_selector(&page, "#submitButton", wait)
             .await?
             .click()
             .await?;
+        if !wait_for_sign_in_result(&page, wait).await? {
+            log_progress(
+                "Login failed, Check credentials",
+                LogLevel::Error,
+                sink,
+                true,
+            );
+            return Err(ScrapError::LoginFailed);
+        }
     }
-    page.wait_for_navigation().await?;
-    sleep(Duration::from_secs(2)).await;
-    let current_url = page
-        .url()
-        .await?
-        .ok_or(ScrapError::NavigationError("No URL strangely".to_string()))?;
-    let current_url = Url::parse(&current_url)
-        .map_err(|_e| ScrapError::NavigationError("URL malformed".to_string()))?;
-    if current_url.host_str() != Some("in4sit.singaporetech.edu.sg") {
-        log_to_front(
-            "Login failed, Check credentials",
+    log_progress("User logged in", LogLevel::Info, sink, true);
+    Ok(page)
+}
+
+/// Opens the landing page in the visible browser and waits for the user to sign in
+/// themselves, however many factors that takes.
+async fn wait_for_manual_login(
+    browser: &Browser,
+    sink: &dyn ProgressSink,
+    timeout: Duration,
+) -> Result<Page, ScrapError> {
+    let page = browser.new_page(LANDING_PAGE).await?;
+    let msg = format!(
+        "Sign in using the browser window, waiting up to {} minutes",
+        timeout.as_secs().div_ceil(60)
+    );
+    log_progress(&msg, LogLevel::Info, sink, true);
+    // a person is typing, checking every second is plenty
+    let wait = WaitOptions {
+        timeout,
+        first_delay: Duration::from_secs(1),
+        max_delay: Duration::from_secs(1),
+    };
+    // the landing page is a portal URL itself, so a portal page only means signed in once the
+    // browser has been through SSO, or when the landing page is up without a sign in form
+    let left_portal = AtomicBool::new(false);
+    let started = Instant::now();
+    let mut reported = started;
+    let signed_in = wait_until("manual sign in", &wait, || {
+        if reported.elapsed() >= Duration::from_secs(30) {
+            let left = timeout.saturating_sub(started.elapsed()).as_secs();
+            let msg = format!("Still waiting for sign in, {}s left", left);
+            log_progress(&msg, LogLevel::Info, sink, true);
+            reported = Instant::now();
+        }
+        let (page, left_portal) = (&page, &left_portal);
+        async move {
+            let Some(url) = page.url().await? else {
+                return Ok(None);
+            };
+            if !is_portal_url(&url) {
+                left_portal.store(true, Ordering::Relaxed);
+                return Ok(None);
+            }
+            if left_portal.load(Ordering::Relaxed) {
+                return Ok(Some(()));
+            }
+            let sign_in_form = page.find_element("#userNameInput").await.is_ok();
+            Ok((url == LANDING_PAGE && !sign_in_form).then_some(()))
+        }
+    })
+    .await;
+    if let Err(e) = signed_in {
+        log_progress(
+            "Sign in was not completed in time",
             LogLevel::Error,
-            app,
+            sink,
             true,
         );
-        Err(ScrapError::LoginFailed)
-    } else {
-        log_to_front("User logged in", LogLevel::Info, app, true);
-        let mut retry_count = 0;
-        while page.url().await? != Some(CALENDER_LINK.to_string()) && retry_count < 3 {
-            page.goto(CALENDER_LINK).await?;
-            page.wait_for_navigation().await?;
-            sleep(Duration::from_secs(1)).await;
-            retry_count += 1;
-            if retry_count >= 2 {
-                log_to_front(
+        return Err(e);
+    }
+    log_progress("User logged in", LogLevel::Info, sink, true);
+    Ok(page)
+}
+
+/// Signs in with a headless browser and stops there, to check credentials before saving or
+/// scheduling with them. The browser is the one a scrape with `options` would use.
+pub async fn test_login(
+    sink: &dyn ProgressSink,
+    username: &str,
+    password: &str,
+    mut options: ScrapOptions,
+) -> Result<(), ScrapError> {
+    options.debug_mode = false;
+    options.manual_login = false;
+    // a remembered session would sign in without checking the password
+    options.user_data_dir = None;
+    let mut browser = launch_browser(sink, &options).await?;
+    let logged_in = log_in(&browser, sink, username, password, &wait_options(&options)).await;
+    let _ = browser.close().await;
+    logged_in.map(|_| ())
+}
+
+/// Opens the study list, returning whether in4SIT showed the term selection page instead.
+async fn open_study_list(
+    page: &Page,
+    sink: &dyn ProgressSink,
+    wait: &WaitOptions,
+) -> Result<bool, ScrapError> {
+    let mut attempt = 1;
+    loop {
+        page.goto(CALENDER_LINK).await?;
+        let loaded = wait_until("the study list or term selection", wait, || async move {
+            if page.find_element(FILTER_BUTTON).await.is_ok() {
+                return Ok(Some(false));
+            }
+            Ok(page.find_element(TERM_RADIO).await.ok().map(|_| true))
+        })
+        .await;
+        match loaded {
+            Ok(selecting) => return Ok(selecting),
+            Err(ScrapError::Timeout(_)) if attempt < 3 => {
+                log_progress(
                     "Attempting to load Calender view again",
                     LogLevel::Warn,
-                    app,
+                    sink,
                     true,
                 );
+                attempt += 1;
             }
+            Err(e) => return Err(e),
         }
+    }
+}
 
-        log_to_front("Loaded Calender view", LogLevel::Info, app, true);
-
-        if options.filter_dropped {
-            page.find_xpath("//*[@id=\"DERIVED_REGFRM1_SA_STUDYLIST_D\"]")
-                .await?
-                .click()
-                .await?;
-            log_to_front(
-                "Unchecked 'Show Dropped Classes'",
-                LogLevel::Info,
-                app,
-                true,
-            );
-        }
+fn same_term(a: &str, b: &str) -> bool {
+    let normalize = |s: &str| {
+        s.split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .to_lowercase()
+    };
+    normalize(a) == normalize(b)
+}
 
-        if options.filter_waitlisted {
-            page.find_xpath("//*[@id=\"DERIVED_REGFRM1_SA_STUDYLIST_W\"]")
-                .await?
-                .click()
-                .await?;
-            log_to_front(
-                "Unchecked 'Show Waitlisted Classes'",
-                LogLevel::Info,
-                app,
-                true,
-            );
+/// Leaves the page on the study list of `term`, going through the term selection page when
+/// in4SIT shows it or another term is displayed. Without a term the first one is picked on
+/// the selection page and whatever is displayed is kept otherwise.
+async fn show_term(
+    page: &Page,
+    sink: &dyn ProgressSink,
+    term: Option<&str>,
+    selecting: bool,
+    wait: &WaitOptions,
+) -> Result<(), ScrapError> {
+    if !selecting {
+        let Some(term) = term else {
+            return Ok(());
+        };
+        let shown = displayed_term(&page.content().await?);
+        if shown.is_some_and(|shown| same_term(&shown.name, term)) {
+            return Ok(());
         }
-
-        page.find_xpath("//*[@id=\"DERIVED_REGFRM1_SA_STUDYLIST_SHOW$14$\"]")
+        log_progress("Changing term", LogLevel::Info, sink, true);
+        wait_for_selector(page, CHANGE_TERM_BUTTON, wait)
             .await?
             .click()
             .await?;
-        page.wait_for_navigation().await?;
-        sleep(Duration::from_secs(2)).await;
-        log_to_front("Filtering done", LogLevel::Info, app, true);
-        let html = page.content().await?;
-
-        if options.debug_mode {
-            log_to_front("Waiting for browser exit", LogLevel::Debug, app, true);
-            log_to_front("Close the browser to continue", LogLevel::Debug, app, true);
-            let _ = browser.wait().await;
+        wait_for_selector(page, TERM_RADIO, wait).await?;
+    }
+
+    let terms = parse_terms(&page.content().await?);
+    let chosen = match term {
+        Some(name) => terms.iter().find(|t| same_term(&t.name, name)),
+        None => terms.first(),
+    };
+    let Some(chosen) = chosen else {
+        let available = terms
+            .iter()
+            .map(|t| t.name.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let msg = format!(
+            "Term {} not found, available terms: {}",
+            term.unwrap_or_default(),
+            available
+        );
+        log_progress(&msg, LogLevel::Error, sink, true);
+        return Err(ScrapError::NavigationError(msg));
+    };
+    let radio = format!("[id='SSR_DUMMY_RECV1$sels${}$$0']", chosen.index);
+    wait_for_selector(page, &radio, wait).await?.click().await?;
+    wait_for_selector(page, TERM_CONTINUE_BUTTON, wait)
+        .await?
+        .click()
+        .await?;
+    wait_for_selector(page, FILTER_BUTTON, wait).await?;
+    let msg = format!("Selected term {}", chosen.name);
+    log_progress(&msg, LogLevel::Info, sink, true);
+    Ok(())
+}
+
+async fn sign_in(
+    browser: &Browser,
+    sink: &dyn ProgressSink,
+    username: &str,
+    password: &str,
+    options: &ScrapOptions,
+    wait: &WaitOptions,
+) -> Result<Page, ScrapError> {
+    if options.manual_login {
+        let timeout = options
+            .manual_login_timeout_secs
+            .map_or(DEFAULT_MANUAL_LOGIN_TIMEOUT, Duration::from_secs);
+        wait_for_manual_login(browser, sink, timeout).await
+    } else {
+        log_in(browser, sink, username, password, wait).await
+    }
+}
+
+async fn list_terms(
+    browser: &Browser,
+    sink: &dyn ProgressSink,
+    username: &str,
+    password: &str,
+    options: &ScrapOptions,
+) -> Result<Vec<Term>, ScrapError> {
+    let wait = wait_options(options);
+    let page = sign_in(browser, sink, username, password, options, &wait).await?;
+    if !open_study_list(&page, sink, &wait).await? {
+        match page.find_element(CHANGE_TERM_BUTTON).await {
+            Ok(button) => {
+                button.click().await?;
+                wait_for_selector(&page, TERM_RADIO, &wait).await?;
+            }
+            // enrolled in a single term, there is nothing to change to
+            Err(_) => return Ok(displayed_term(&page.content().await?).into_iter().collect()),
         }
-        Ok(html)
     }
+    Ok(parse_terms(&page.content().await?))
+}
+
+/// Signs in and lists the terms on the term selection page, or only the displayed term when
+/// the student is enrolled in a single one.
+pub async fn fetch_terms(
+    sink: &dyn ProgressSink,
+    username: &str,
+    password: &str,
+    options: ScrapOptions,
+) -> Result<Vec<Term>, ScrapError> {
+    let mut browser = launch_browser(sink, &options).await?;
+    let terms = list_terms(&browser, sink, username, password, &options).await;
+    let _ = browser.close().await;
+    if let Ok(terms) = &terms {
+        let msg = format!("Found {} terms", terms.len());
+        log_progress(&msg, LogLevel::Info, sink, true);
+    }
+    terms
+}
+
+/// Shows every class of the displayed study list, dropped and waitlisted ones included, and
+/// returns it.
+async fn read_study_list(
+    page: &Page,
+    sink: &dyn ProgressSink,
+    wait: &WaitOptions,
+) -> Result<String, ScrapError> {
+    log_progress("Loaded Calender view", LogLevel::Info, sink, true);
+
+    // everything is scraped and filtered on export, so in4SIT must not hide any class
+    for checkbox in [SHOW_DROPPED_CHECKBOX, SHOW_WAITLISTED_CHECKBOX] {
+        let element = wait_for_selector(page, checkbox, wait).await?;
+        if element.attribute("checked").await?.is_none() {
+            element.click().await?;
+        }
+    }
+
+    let network = NetworkMonitor::start(page).await?;
+    wait_for_selector(page, FILTER_BUTTON, wait)
+        .await?
+        .click()
+        .await?;
+    network.wait_for_idle(NETWORK_IDLE, wait).await?;
+    log_progress("Filtering done", LogLevel::Info, sink, true);
+    Ok(page.content().await?)
+}
+
+// `None` stands for whichever term in4SIT shows
+fn requested_terms(options: &ScrapOptions) -> Vec<Option<&str>> {
+    if options.terms.is_empty() {
+        vec![options.term.as_deref()]
+    } else {
+        options.terms.iter().map(|t| Some(t.as_str())).collect()
+    }
+}
+
+/// Scrapes the study list of every requested term in one browser session, returning one
+/// page per term in the order they were requested.
+pub async fn start_scrap(
+    sink: &dyn ProgressSink,
+    username: &str,
+    password: &str,
+    options: ScrapOptions,
+) -> Result<Vec<String>, ScrapError> {
+    let wait = wait_options(&options);
+    let mut browser = launch_browser(sink, &options).await?;
+    let page = sign_in(&browser, sink, username, password, &options, &wait).await?;
+
+    let mut selecting = open_study_list(&page, sink, &wait).await?;
+    let mut pages = vec![];
+    for term in requested_terms(&options) {
+        show_term(&page, sink, term, selecting, &wait).await?;
+        // the next term is reached through the change term link of this study list
+        selecting = false;
+        pages.push(read_study_list(&page, sink, &wait).await?);
+    }
+
+    if options.debug_mode {
+        log_progress("Waiting for browser exit", LogLevel::Debug, sink, true);
+        log_progress("Close the browser to continue", LogLevel::Debug, sink, true);
+        let _ = browser.wait().await;
+    }
+    Ok(pages)
+}
+
+/// Parses a study list page saved from the user's own browser, for when logging in through
+/// the scraper is not possible.
+pub async fn import_saved_html(
+    path: &str,
+    sink: &dyn ProgressSink,
+) -> Result<(ScrapResult, Vec<CourseInfo>), ScrapError> {
+    log_progress(&format!("Reading {}", path), LogLevel::Info, sink, true);
+    let bytes = tokio::fs::read(path)
+        .await
+        .map_err(|e| ScrapError::FileError(e.to_string()))?;
+    let html = String::from_utf8_lossy(&bytes).into_owned();
+    if is_portal_frame_page(&html) {
+        return Err(ScrapError::FileError(
+            "This is the portal page around the study list, not the study list itself. Right \
+             click inside the timetable, open the frame on its own and save that page instead"
+                .to_string(),
+        ));
+    }
+    extract_timetable_from_html(html, sink)
+}
+
+// Saving the portal from the address bar stores the outer page, whose timetable lives in the
+// TargetContent iframe and is left out of the saved file.
+fn is_portal_frame_page(html: &str) -> bool {
+    let doc = Html::parse_document(html);
+    let frame = Selector::parse("iframe#ptifrmtgtframe, iframe[name='TargetContent']").unwrap();
+    let study_list = Selector::parse("#ACE_STDNT_ENRL_SSV2\\$0").unwrap();
+    doc.select(&frame).next().is_some() && doc.select(&study_list).next().is_none()
+}
+
+/// The term of a study list page, from its title.
+pub fn displayed_term(html: &str) -> Option<Term> {
+    let doc = Html::parse_document(html);
+    let term_selector = Selector::parse("#DERIVED_REGFRM1_SSR_STDNTKEY_DESCR\\$11\\$").unwrap();
+    // Title reads "<term> | <career> | <institution>"
+    let title = get_inner_text_from_element(&doc.select(&term_selector).next()?);
+    let mut parts = title
+        .split('|')
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty());
+    Some(Term {
+        index: 0,
+        name: parts.next()?,
+        career: parts.next(),
+        institution: parts.next(),
+    })
+}
+
+/// Lists the terms of the term selection page in the order they are shown.
+pub fn parse_terms(html: &str) -> Vec<Term> {
+    let doc = Html::parse_document(html);
+    let text = |id: String| {
+        let selector = Selector::parse(&format!("[id='{}']", id)).ok()?;
+        doc.select(&selector)
+            .next()
+            .map(|e| get_inner_text_from_element(&e))
+            .filter(|t| !t.is_empty())
+    };
+    (0..)
+        .map_while(|index| {
+            Some(Term {
+                index,
+                name: text(format!("TERM_CAR${}", index))?,
+                career: text(format!("CAREER${}", index)),
+                institution: text(format!("INSTITUTION${}", index)),
+            })
+        })
+        .collect()
+}
+
+/// Splits a course name like "CSC1107 - Operating Systems" into its subject code, catalog
+/// number and title.
+fn split_course_name(name: &str) -> (Option<String>, Option<String>, Option<String>) {
+    let (code, title) = name.split_once(" - ").unwrap_or((name, ""));
+    let code = code.trim();
+    let digits = code
+        .find(|c: char| c.is_ascii_digit())
+        .unwrap_or(code.len());
+    let (subject, catalog_number) = code.split_at(digits);
+    let non_empty = |s: &str| Some(s.trim().to_string()).filter(|s| !s.is_empty());
+    (
+        non_empty(subject),
+        non_empty(catalog_number),
+        non_empty(title),
+    )
+}
+
+/// Parses the study list of every scraped term, tagging each course with its term. Skip
+/// counts add up and the term of the result names every term.
+pub fn extract_timetables(
+    pages: Vec<String>,
+    sink: &dyn ProgressSink,
+) -> Result<(ScrapResult, Vec<CourseInfo>), ScrapError> {
+    let mut results = ScrapResult {
+        term: None,
+        skipped_unknown_course_count: 0,
+        skipped_table_entry_count: 0,
+        errors_present: false,
+        changes: vec![],
+    };
+    let mut terms = vec![];
+    let mut courses = vec![];
+    for html in pages {
+        let (r, course_info) = extract_timetable_from_html(html, sink)?;
+        terms.extend(r.term);
+        results.skipped_unknown_course_count = results
+            .skipped_unknown_course_count
+            .saturating_add(r.skipped_unknown_course_count);
+        results.skipped_table_entry_count = results
+            .skipped_table_entry_count
+            .saturating_add(r.skipped_table_entry_count);
+        results.errors_present |= r.errors_present;
+        courses.extend(course_info);
+    }
+    results.term = (!terms.is_empty()).then(|| terms.join(", "));
+    Ok((results, courses))
 }
 
 pub fn extract_timetable_from_html(
     html: String,
-    app: &AppHandle,
+    sink: &dyn ProgressSink,
 ) -> Result<(ScrapResult, Vec<CourseInfo>), ScrapError> {
-    log_to_front("Started processing HTML", LogLevel::Info, app, true);
+    log_progress("Started processing HTML", LogLevel::Info, sink, true);
+    let term = displayed_term(&html).map(|t| t.name);
+    let doc = Html::parse_document(&html);
+    if let Some(term) = &term {
+        log_progress(&format!("Term: {}", term), LogLevel::Info, sink, true);
+    }
     let mut results = ScrapResult {
+        term,
         skipped_unknown_course_count: 0,
         skipped_table_entry_count: 0,
         errors_present: false,
+        changes: vec![],
     };
-    let doc = Html::parse_document(&html);
     let table_selector = Selector::parse("#ACE_STDNT_ENRL_SSV2\\$0 > tbody").unwrap();
     let main_table = doc
         .select(&table_selector)
@@ -230,19 +721,13 @@ pub fn extract_timetable_from_html(
         )?;
     let course_tables = main_table
         .child_elements()
-        .filter_map(|e| {
-            if !get_inner_text_from_element(&e).is_empty() {
-                Some(e)
-            } else {
-                None
-            }
-        })
+        .filter(|e| !get_inner_text_from_element(e).is_empty())
         .collect::<Vec<_>>();
 
-    log_to_front(
+    log_progress(
         &format!("Have {} courses", course_tables.len()),
         LogLevel::Info,
-        app,
+        sink,
         true,
     );
 
@@ -257,30 +742,46 @@ pub fn extract_timetable_from_html(
                 })
                 .collect::<Vec<String>>()
                 .first()
-                .map(|name| name.clone())
+                .cloned()
                 .or_else(|| {
                     let e = ScrapError::HtmlParseError("Course name selector, not found".to_string());
-                    log_to_front(&e.to_string(), LogLevel::Error, app, false);
-                    log_to_front("Course skipped, verify generated timetable.", LogLevel::Error, app, true);
+                    log_progress(&e.to_string(), LogLevel::Error, sink, false);
+                    log_progress("Course skipped, verify generated timetable.", LogLevel::Error, sink, true);
                     results.skipped_unknown_course_count += 1;
                     None
                 })?;
-            log_to_front(&format!("Parsing {} timetable", course_name), LogLevel::Info, app, true);
+            log_progress(&format!("Parsing {} timetable", course_name), LogLevel::Info, sink, true);
             let table_entry_selector = Selector::parse("table.PSLEVEL3GRIDWBO table.PSLEVEL3GRID > tbody").unwrap();
             let course_timetable_node = frag.select(&table_entry_selector)
                 .collect::<Vec<_>>()
                 .get(1)
-                .map(|e| e.clone())
+                .copied()
                 .or_else(|| {
                     let e = ScrapError::HtmlParseError("Timetable selector, not found".to_string());
-                    log_to_front(&e.to_string(), LogLevel::Error, app, false);
-                    log_to_front(&format!("{} skipped, verify generated timetable.", course_name), LogLevel::Error, app, true);
+                    log_progress(&e.to_string(), LogLevel::Error, sink, false);
+                    log_progress(&format!("{} skipped, verify generated timetable.", course_name), LogLevel::Error, sink, true);
                     results.skipped_unknown_course_count += 1;
                     results.errors_present = true;
                     None
                 })?;
+            // the header table above the meeting grid has a single row of status, units and grading
+            let header_node = frag.select(&table_entry_selector).next();
+            let header_text = |id_prefix: &str| {
+                let selector = Selector::parse(&format!("[id^='{}']", id_prefix)).unwrap();
+                header_node?
+                    .select(&selector)
+                    .next()
+                    .map(|e| get_inner_text_from_element(&e))
+                    .filter(|t| !t.is_empty())
+            };
+            let (subject, catalog_number, title) = split_course_name(&course_name);
+            let units = header_text("DERIVED_REGFRM1_UNT_TAKEN$").and_then(|t| t.parse().ok());
+            let grading_basis = header_text("GB_DESCR$");
+            let enrollment_status = header_text("STATUS$").map(|t| EnrollmentStatus::from_portal(&t));
             let mut current_entry_type = EntryType::Lecture;
+            let mut current_component = String::new();
             let mut current_section = "ALL".to_string();
+            let mut current_class_number = None;
             let course_timetable_entries = course_timetable_node
                 .child_elements()
                 .enumerate()
@@ -292,7 +793,13 @@ pub fn extract_timetable_from_html(
                         let mut datetime_string = String::new();
 
                         for (i, cell) in inner_row.iter().enumerate() {
-                            if i == 2 {
+                            if i == 0 {
+                                // Class number, only on the first row of a class
+                                let text = get_inner_text_from_element(cell);
+                                if !text.is_empty() {
+                                    current_class_number = Some(text);
+                                }
+                            } else if i == 2 {
                                 // parse an Entry type
                                 let text = get_inner_text_from_element(cell);
                                 if !text.is_empty() {
@@ -302,14 +809,21 @@ pub fn extract_timetable_from_html(
                                         "Laboratory" => EntryType::Lab,
                                         "Lecture" => EntryType::Lecture,
                                         "Workshop" => EntryType::Workshop,
+                                        "Seminar" => EntryType::Seminar,
+                                        "Studio" => EntryType::Studio,
+                                        "Project" => EntryType::Project,
+                                        "Practical" => EntryType::Practical,
+                                        "Exam" | "Examination" => EntryType::Exam,
+                                        "Online" | "Online Learning" | "Asynchronous" | "Asynchronous Learning" => EntryType::Online,
                                         _ => {
+                                            // nothing is lost, the label is kept as it is
                                             let msg = format!("Encountered unknown entry type when parsing table: {}, no matches found", text);
-                                            log_to_front(&msg, LogLevel::Warn, app, true);
-                                            log_to_front("Default to 'unknown', continuing...", LogLevel::Warn, app, true);
-                                            results.errors_present = true;
-                                            EntryType::Unknown
+                                            log_progress(&msg, LogLevel::Warn, sink, true);
+                                            log_progress(&format!("Keeping it as '{}', continuing...", text), LogLevel::Warn, sink, true);
+                                            EntryType::Other(text.clone())
                                         }
                                     };
+                                    current_component = text;
                                 }
                             } else if i == 1 {
                                 // Parse Class section
@@ -343,7 +857,7 @@ pub fn extract_timetable_from_html(
                             .map(|s| s.trim().to_string())
                             .collect::<Vec<_>>();
                         if datetime_string.contains("TBA") {
-                            log_to_front(&format!("Table entry skipped for {}, meeting info not available", course_name), LogLevel::Warn, app, true);
+                            log_progress(&format!("Table entry skipped for {}, meeting info not available", course_name), LogLevel::Warn, sink, true);
                             results.skipped_table_entry_count += 1;
                             None
                         } else {
@@ -351,11 +865,13 @@ pub fn extract_timetable_from_html(
                                 let e = ScrapError::HtmlParseError(e);
                                 results.errors_present = true;
                                 results.skipped_table_entry_count += 1;
-                                log_to_front(&e.to_string(), LogLevel::Error, app, false);
-                                log_to_front(&format!("Table entry skipped for {}, CHECK results", course_name), LogLevel::Error, app, true);
+                                log_progress(&e.to_string(), LogLevel::Error, sink, false);
+                                log_progress(&format!("Table entry skipped for {}, CHECK results", course_name), LogLevel::Error, sink, true);
                             }).ok()?;
                             Some(TimeTableEntry {
                                 entry_type: current_entry_type.clone(),
+                                component: current_component.clone(),
+                                class_number: current_class_number.clone(),
                                 class_section: current_section.clone(),
                                 location,
                                 instructors: instructor_vec,
@@ -371,6 +887,13 @@ pub fn extract_timetable_from_html(
                 .collect::<Vec<_>>();
             Some(CourseInfo {
                 course_name,
+                term: results.term.clone(),
+                subject,
+                catalog_number,
+                title,
+                units,
+                grading_basis,
+                enrollment_status,
                 table_entries: course_timetable_entries,
             })
         })
@@ -0,0 +1,149 @@
+use crate::models::{CourseInfo, ExportSnapshot, SavedSchedule, SavedSubscription, SavedTimetable};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+const LAST_SCRAPE_FILE: &str = "last_scrape.json";
+const BROWSER_PROFILE_DIR: &str = "browser-profile";
+const BUNDLED_CHROMIUM_DIR: &str = "chromium";
+const SUBSCRIPTION_FILE: &str = "subscription.json";
+const SCHEDULE_FILE: &str = "schedule.json";
+
+pub fn last_scrape_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(LAST_SCRAPE_FILE)
+}
+
+pub async fn save_last_scrape(data_dir: &Path, saved: &SavedTimetable) -> Result<(), String> {
+    tokio::fs::create_dir_all(data_dir)
+        .await
+        .map_err(|e| e.to_string())?;
+    let json = serde_json::to_string(saved).map_err(|e| e.to_string())?;
+    tokio::fs::write(last_scrape_path(data_dir), json)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+pub fn browser_profile_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(BROWSER_PROFILE_DIR)
+}
+
+/// Where the `bundled-chromium` feature downloads its browser to.
+pub fn bundled_chromium_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(BUNDLED_CHROMIUM_DIR)
+}
+
+/// Signs out of every site by deleting the persistent browser profile.
+pub async fn clear_browser_profile(data_dir: &Path) -> Result<(), String> {
+    match tokio::fs::remove_dir_all(browser_profile_path(data_dir)).await {
+        Err(e) if e.kind() != ErrorKind::NotFound => Err(e.to_string()),
+        _ => Ok(()),
+    }
+}
+
+/// Returns `None` when nothing was saved yet or the file can no longer be read, e.g. after
+/// an update changed its format.
+pub fn load_last_scrape(data_dir: &Path) -> Option<SavedTimetable> {
+    let json = std::fs::read_to_string(last_scrape_path(data_dir)).ok()?;
+    serde_json::from_str(&json).ok()
+}
+
+pub async fn clear_last_scrape(data_dir: &Path) -> Result<(), String> {
+    match tokio::fs::remove_file(last_scrape_path(data_dir)).await {
+        Err(e) if e.kind() != ErrorKind::NotFound => Err(e.to_string()),
+        _ => Ok(()),
+    }
+}
+
+pub fn subscription_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(SUBSCRIPTION_FILE)
+}
+
+pub async fn save_subscription(data_dir: &Path, saved: &SavedSubscription) -> Result<(), String> {
+    tokio::fs::create_dir_all(data_dir)
+        .await
+        .map_err(|e| e.to_string())?;
+    let json = serde_json::to_string(saved).map_err(|e| e.to_string())?;
+    tokio::fs::write(subscription_path(data_dir), json)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Returns `None` when no subscription server was ever started.
+pub fn load_subscription(data_dir: &Path) -> Option<SavedSubscription> {
+    let json = std::fs::read_to_string(subscription_path(data_dir)).ok()?;
+    serde_json::from_str(&json).ok()
+}
+
+pub fn schedule_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(SCHEDULE_FILE)
+}
+
+pub async fn save_schedule(data_dir: &Path, saved: &SavedSchedule) -> Result<(), String> {
+    tokio::fs::create_dir_all(data_dir)
+        .await
+        .map_err(|e| e.to_string())?;
+    let json = serde_json::to_string(saved).map_err(|e| e.to_string())?;
+    tokio::fs::write(schedule_path(data_dir), json)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Returns `None` when no re-scrape is scheduled.
+pub fn load_schedule(data_dir: &Path) -> Option<SavedSchedule> {
+    let json = std::fs::read_to_string(schedule_path(data_dir)).ok()?;
+    serde_json::from_str(&json).ok()
+}
+
+pub async fn clear_schedule(data_dir: &Path) -> Result<(), String> {
+    match tokio::fs::remove_file(schedule_path(data_dir)).await {
+        Err(e) if e.kind() != ErrorKind::NotFound => Err(e.to_string()),
+        _ => Ok(()),
+    }
+}
+
+fn with_file_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+/// `timetable.ics` is recorded in `timetable.ics.snapshot.json`.
+pub fn export_snapshot_path(ics_path: &Path) -> PathBuf {
+    with_file_suffix(ics_path, ".snapshot.json")
+}
+
+/// Cancellations for `timetable.ics` are written to `timetable.cancel.ics`.
+pub fn cancelled_ics_path(ics_path: &Path) -> PathBuf {
+    let stem = ics_path.file_stem().unwrap_or_default().to_os_string();
+    with_file_suffix(&ics_path.with_file_name(stem), ".cancel.ics")
+}
+
+pub async fn save_export_snapshot(
+    ics_path: &Path,
+    snapshot: &ExportSnapshot,
+) -> Result<(), String> {
+    let json = serde_json::to_string(snapshot).map_err(|e| e.to_string())?;
+    tokio::fs::write(export_snapshot_path(ics_path), json)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Returns `None` when the file was never exported to, or its snapshot is unreadable.
+pub async fn load_export_snapshot(ics_path: &Path) -> Option<ExportSnapshot> {
+    let json = tokio::fs::read_to_string(export_snapshot_path(ics_path))
+        .await
+        .ok()?;
+    serde_json::from_str(&json).ok()
+}
+
+/// Reads a timetable snapshot: a saved scrape, the snapshot of an ICS export or the output of
+/// the JSON export.
+pub async fn load_snapshot_file(path: &Path) -> Result<Vec<CourseInfo>, String> {
+    let json = tokio::fs::read_to_string(path)
+        .await
+        .map_err(|e| format!("{}: {}", path.display(), e))?;
+    serde_json::from_str::<SavedTimetable>(&json)
+        .map(|saved| saved.courses)
+        .or_else(|_| serde_json::from_str::<ExportSnapshot>(&json).map(|s| s.courses))
+        .or_else(|_| serde_json::from_str::<Vec<CourseInfo>>(&json))
+        .map_err(|e| format!("{} is not a timetable snapshot: {}", path.display(), e))
+}
@@ -0,0 +1,149 @@
+use crate::models::SubscriptionOptions;
+use std::future::Future;
+use std::net::{IpAddr, Ipv4Addr, UdpSocket};
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::async_runtime::JoinHandle;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use uuid::Uuid;
+
+const MAX_REQUEST_HEAD: usize = 8 * 1024;
+// a client that connects and never sends a request would otherwise keep its task alive forever
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+// accept fails e.g. when the process runs out of file descriptors, retrying right away would spin
+const ACCEPT_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// A running calendar subscription server, stopped by [`SubscriptionServer::stop`].
+pub struct SubscriptionServer {
+    pub url: String,
+    pub token: String,
+    pub port: u16,
+    task: JoinHandle<()>,
+}
+
+impl SubscriptionServer {
+    /// Returns once the port is free again, so a server can be started on it right away.
+    pub async fn stop(self) {
+        self.task.abort();
+        let _ = self.task.await;
+    }
+}
+
+// Connecting a UDP socket sends nothing, it only makes the OS pick the interface that
+// would route to the internet, which is the address other devices on the network can reach.
+fn lan_address() -> Option<IpAddr> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).ok()?;
+    socket.connect((Ipv4Addr::new(8, 8, 8, 8), 80)).ok()?;
+    Some(socket.local_addr().ok()?.ip())
+}
+
+/// A random token for the subscription URL.
+pub fn new_token() -> String {
+    Uuid::new_v4().simple().to_string()
+}
+
+/// Serves the calendar returned by `calendar` at a URL containing `token`, so only whoever
+/// was given the URL can subscribe. The calendar is rendered again on every request,
+/// calendar apps pick up re-scrapes on their next refresh.
+pub async fn start_subscription_server<F, Fut>(
+    options: &SubscriptionOptions,
+    token: &str,
+    calendar: F,
+) -> Result<SubscriptionServer, String>
+where
+    F: Fn() -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = String> + Send + 'static,
+{
+    let bind_ip = if options.allow_lan {
+        Ipv4Addr::UNSPECIFIED
+    } else {
+        Ipv4Addr::LOCALHOST
+    };
+    let listener = TcpListener::bind((bind_ip, options.port))
+        .await
+        .map_err(|e| format!("Could not listen on port {}: {}", options.port, e))?;
+    let port = listener.local_addr().map_err(|e| e.to_string())?.port();
+    let host = if options.allow_lan {
+        lan_address().unwrap_or(IpAddr::V4(Ipv4Addr::LOCALHOST))
+    } else {
+        IpAddr::V4(Ipv4Addr::LOCALHOST)
+    };
+    let path = format!("/{}/timetable.ics", token);
+    let url = format!("http://{}:{}{}", host, port, path);
+
+    let calendar = Arc::new(calendar);
+    let task = tauri::async_runtime::spawn(async move {
+        loop {
+            let stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    eprintln!("Subscription server could not accept a connection: {}", e);
+                    tokio::time::sleep(ACCEPT_RETRY_DELAY).await;
+                    continue;
+                }
+            };
+            let calendar = calendar.clone();
+            let path = path.clone();
+            tauri::async_runtime::spawn(async move {
+                let _ = respond(stream, &path, calendar.as_ref()).await;
+            });
+        }
+    });
+    Ok(SubscriptionServer {
+        url,
+        token: token.to_string(),
+        port,
+        task,
+    })
+}
+
+async fn respond<F, Fut>(mut stream: TcpStream, path: &str, calendar: &F) -> std::io::Result<()>
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = String>,
+{
+    let mut head = vec![];
+    let mut buf = [0; 1024];
+    let read_head = async {
+        while !head.windows(4).any(|w| w == b"\r\n\r\n") && head.len() < MAX_REQUEST_HEAD {
+            let n = stream.read(&mut buf).await?;
+            if n == 0 {
+                break;
+            }
+            head.extend_from_slice(&buf[..n]);
+        }
+        Ok::<_, std::io::Error>(())
+    };
+    tokio::time::timeout(REQUEST_TIMEOUT, read_head)
+        .await
+        .map_err(|_| std::io::Error::from(std::io::ErrorKind::TimedOut))??;
+    let head = String::from_utf8_lossy(&head);
+    let mut request_line = head.lines().next().unwrap_or_default().split_whitespace();
+    let method = request_line.next().unwrap_or_default();
+    // calendar apps sometimes append a query string to bust caches
+    let target = request_line
+        .next()
+        .and_then(|t| t.split('?').next())
+        .unwrap_or_default();
+
+    let (status, content_type, body) = match method {
+        "GET" | "HEAD" if target == path => ("200 OK", "text/calendar", calendar().await),
+        "GET" | "HEAD" => ("404 Not Found", "text/plain", "Not Found".to_string()),
+        _ => (
+            "405 Method Not Allowed",
+            "text/plain",
+            "Method Not Allowed".to_string(),
+        ),
+    };
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}; charset=utf-8\r\nContent-Length: {}\r\n\
+         Cache-Control: no-cache\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        if method == "HEAD" { "" } else { &body }
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
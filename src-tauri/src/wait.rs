@@ -0,0 +1,142 @@
+use crate::scrap::ScrapError;
+use chromiumoxide::cdp::browser_protocol::network::{
+    EventLoadingFailed, EventLoadingFinished, EventRequestWillBeSent, RequestId,
+};
+use chromiumoxide::{Element, Page};
+use futures::stream::{self, Stream, StreamExt};
+use std::collections::HashSet;
+use std::future::Future;
+use std::pin::Pin;
+use std::time::{Duration, Instant};
+use tokio::time::sleep;
+
+#[derive(Debug, Clone, Copy)]
+pub struct WaitOptions {
+    pub timeout: Duration,
+    // delay before the second check, doubled after every check up to `max_delay`
+    pub first_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for WaitOptions {
+    fn default() -> Self {
+        WaitOptions {
+            timeout: Duration::from_secs(30),
+            first_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(2),
+        }
+    }
+}
+
+fn timed_out(condition: &str, options: &WaitOptions) -> ScrapError {
+    ScrapError::Timeout(format!(
+        "waiting for {} after {}s",
+        condition,
+        options.timeout.as_secs()
+    ))
+}
+
+/// Checks `check` until it returns a value, backing off between checks. An error from
+/// `check` ends the wait, running out of time fails with a timeout naming `condition`.
+pub async fn wait_until<T, F, Fut>(
+    condition: &str,
+    options: &WaitOptions,
+    mut check: F,
+) -> Result<T, ScrapError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<Option<T>, ScrapError>>,
+{
+    let started = Instant::now();
+    let mut delay = options.first_delay;
+    loop {
+        if let Some(value) = check().await? {
+            return Ok(value);
+        }
+        let Some(left) = options.timeout.checked_sub(started.elapsed()) else {
+            return Err(timed_out(condition, options));
+        };
+        sleep(delay.min(left)).await;
+        delay = (delay * 2).min(options.max_delay);
+    }
+}
+
+pub async fn wait_for_selector(
+    page: &Page,
+    selector: &str,
+    options: &WaitOptions,
+) -> Result<Element, ScrapError> {
+    wait_until(selector, options, || async move {
+        Ok(page.find_element(selector).await.ok())
+    })
+    .await
+}
+
+/// A request of a page starting, or finishing either way.
+pub enum NetworkActivity {
+    Started(RequestId),
+    Done(RequestId),
+}
+
+// PeopleSoft pages keep long-polling and beacon requests open, so like Puppeteer's
+// networkidle2 a page is idle with this many requests still in flight
+const IDLE_IN_FLIGHT: usize = 2;
+
+/// Follows the requests of a page. Start it before the action that triggers the requests,
+/// otherwise a request already in flight goes unnoticed.
+pub struct NetworkMonitor {
+    activity: Pin<Box<dyn Stream<Item = NetworkActivity> + Send>>,
+}
+
+impl NetworkMonitor {
+    pub async fn start(page: &Page) -> Result<Self, ScrapError> {
+        let started = page
+            .event_listener::<EventRequestWillBeSent>()
+            .await?
+            .map(|e| NetworkActivity::Started(e.request_id.clone()));
+        let finished = page
+            .event_listener::<EventLoadingFinished>()
+            .await?
+            .map(|e| NetworkActivity::Done(e.request_id.clone()));
+        let failed = page
+            .event_listener::<EventLoadingFailed>()
+            .await?
+            .map(|e| NetworkActivity::Done(e.request_id.clone()));
+        let activity = stream::select(started, stream::select(finished, failed));
+        Ok(Self::from_activity(activity))
+    }
+
+    pub fn from_activity(activity: impl Stream<Item = NetworkActivity> + Send + 'static) -> Self {
+        NetworkMonitor {
+            activity: Box::pin(activity),
+        }
+    }
+
+    /// Resolves once no more than two requests have been in flight for `idle`.
+    pub async fn wait_for_idle(
+        mut self,
+        idle: Duration,
+        options: &WaitOptions,
+    ) -> Result<(), ScrapError> {
+        let started = Instant::now();
+        let mut in_flight = HashSet::new();
+        loop {
+            let left = options.timeout.saturating_sub(started.elapsed());
+            if left.is_zero() {
+                return Err(timed_out("the network to go idle", options));
+            }
+            match tokio::time::timeout(idle.min(left), self.activity.next()).await {
+                Err(_) if in_flight.len() <= IDLE_IN_FLIGHT && left >= idle => return Ok(()),
+                Err(_) => {}
+                Ok(Some(NetworkActivity::Started(id))) => {
+                    in_flight.insert(id);
+                }
+                Ok(Some(NetworkActivity::Done(id))) => {
+                    in_flight.remove(&id);
+                }
+                // the page is gone, nothing is left to load
+                Ok(None) => return Ok(()),
+            }
+        }
+    }
+}
@@ -0,0 +1,15 @@
+use export_sit_timetable_lib::browser::{check_browser, find_browser};
+
+#[test]
+fn configured_browser_overrides_discovery() {
+    let exe = std::env::temp_dir().join(format!("sit-timetable-chrome-{}", std::process::id()));
+    std::fs::write(&exe, "").unwrap();
+    let found = find_browser(Some(&exe));
+    let _ = std::fs::remove_file(&exe);
+    assert_eq!(found.as_deref(), Some(exe.as_path()));
+
+    let check = check_browser(Some(&exe));
+    assert!(check.configured);
+    assert_eq!(check.path, None);
+    assert_eq!(check.searched, [exe]);
+}
@@ -0,0 +1,43 @@
+// shared by several test crates, each one only uses some of these
+#![allow(dead_code)]
+
+use chrono::{DateTime, NaiveDate};
+use chrono_tz::Tz;
+use export_sit_timetable_lib::helper::PORTAL_TZ;
+use export_sit_timetable_lib::models::{CourseInfo, EntryType, TimeTableEntry};
+
+pub fn at(date: (i32, u32, u32), hour: u32) -> DateTime<Tz> {
+    NaiveDate::from_ymd_opt(date.0, date.1, date.2)
+        .unwrap()
+        .and_hms_opt(hour, 0, 0)
+        .unwrap()
+        .and_local_timezone(PORTAL_TZ)
+        .unwrap()
+}
+
+/// A two hour lecture starting at `hour`.
+pub fn entry(date: (i32, u32, u32), hour: u32, location: &str, instructor: &str) -> TimeTableEntry {
+    TimeTableEntry {
+        entry_type: EntryType::Lecture,
+        component: "Lecture".to_string(),
+        class_number: None,
+        class_section: "P1".to_string(),
+        location: location.to_string(),
+        instructors: vec![instructor.to_string()],
+        start_datetime: at(date, hour),
+        end_datetime: at(date, hour + 2),
+    }
+}
+
+/// The usual 9 to 11 lecture by Alice Tan.
+pub fn lecture(date: (i32, u32, u32), location: &str) -> TimeTableEntry {
+    entry(date, 9, location, "Alice Tan")
+}
+
+pub fn course(entries: Vec<TimeTableEntry>) -> Vec<CourseInfo> {
+    vec![CourseInfo {
+        course_name: "CSC1107 - Operating Systems".to_string(),
+        table_entries: entries,
+        ..Default::default()
+    }]
+}
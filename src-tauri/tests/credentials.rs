@@ -0,0 +1,23 @@
+// the fallback key is derived from /etc/machine-id, which only Linux has
+#![cfg(target_os = "linux")]
+
+use export_sit_timetable_lib::credentials::{load_from_file, save_to_file, Credentials};
+
+#[test]
+fn fallback_file_round_trips_without_plaintext() {
+    let dir =
+        std::env::temp_dir().join(format!("sit-timetable-credentials-{}", std::process::id()));
+    let credentials = Credentials {
+        username: "2100123@sit.singaporetech.edu.sg".to_string(),
+        password: "hunter2-secret".to_string(),
+    };
+    save_to_file(&dir, &credentials).unwrap();
+    let raw = std::fs::read(dir.join("credentials.enc")).unwrap();
+    let loaded = load_from_file(&dir).unwrap();
+    let _ = std::fs::remove_dir_all(&dir);
+
+    assert!(!raw.windows(14).any(|w| w == b"hunter2-secret"));
+    assert_eq!(loaded.username, credentials.username);
+    assert_eq!(loaded.password, credentials.password);
+    assert!(!format!("{:?}", loaded).contains("hunter2"));
+}
@@ -0,0 +1,52 @@
+mod common;
+
+use common::{course, entry};
+use export_sit_timetable_lib::diff::{diff_timetables, ChangeKind};
+
+#[test]
+fn unchanged_timetable_has_no_changes() {
+    let old = course(vec![entry((2025, 1, 13), 9, "SR-5A", "Alice Tan")]);
+    assert!(diff_timetables(&old, &old.clone()).is_empty());
+}
+
+#[test]
+fn changes_are_classified() {
+    let old = course(vec![
+        entry((2025, 1, 13), 9, "SR-5A", "Alice Tan"),
+        entry((2025, 1, 20), 9, "SR-5A", "Alice Tan"),
+        entry((2025, 1, 27), 9, "SR-5A", "Alice Tan"),
+        entry((2025, 1, 30), 9, "SR-5A", "Alice Tan"),
+    ]);
+    let new = course(vec![
+        entry((2025, 1, 13), 9, "LT-1", "Alice Tan"),
+        entry((2025, 1, 20), 9, "SR-5A", "Benjamin Lee"),
+        entry((2025, 1, 29), 14, "SR-5A", "Alice Tan"),
+        entry((2025, 1, 31), 9, "SR-5A", "Alice Tan"),
+        entry((2025, 1, 3), 9, "SR-5A", "Alice Tan"),
+    ]);
+    let kinds = diff_timetables(&old, &new)
+        .iter()
+        .map(|c| c.kind)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        kinds,
+        [
+            ChangeKind::Added,
+            ChangeKind::Relocated,
+            ChangeKind::InstructorChanged,
+            ChangeKind::Rescheduled,
+            ChangeKind::Rescheduled,
+        ]
+    );
+}
+
+#[test]
+fn class_moved_to_another_week_is_removed_and_added() {
+    let old = course(vec![entry((2025, 1, 13), 9, "SR-5A", "Alice Tan")]);
+    let new = course(vec![entry((2025, 1, 20), 9, "SR-5A", "Alice Tan")]);
+    let changes = diff_timetables(&old, &new);
+    assert_eq!(changes.len(), 2);
+    assert_eq!(changes[0].kind, ChangeKind::Removed);
+    assert_eq!(changes[1].kind, ChangeKind::Added);
+    assert!(changes[0].describe().contains("removed"));
+}
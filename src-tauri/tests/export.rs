@@ -0,0 +1,258 @@
+mod common;
+
+use common::{course, entry, lecture};
+use export_sit_timetable_lib::export::{courses_to_ics, export_ics, ExportFormat};
+use export_sit_timetable_lib::helper::{try_parse_string_to_start_end_dt, PORTAL_TZ};
+use export_sit_timetable_lib::models::{
+    CourseInfo, EnrollmentStatus, EntryType, EntryTypeStyle, ExportOptions, TimeTableEntry,
+};
+use export_sit_timetable_lib::progress::CollectingSink;
+use export_sit_timetable_lib::scrap::extract_timetable_from_html;
+use std::path::PathBuf;
+
+#[test]
+fn incremental_export_only_contains_changes() {
+    let options = ExportOptions {
+        incremental: true,
+        ..Default::default()
+    };
+    let old = course(vec![
+        lecture((2025, 1, 13), "SR-5A"),
+        lecture((2025, 1, 20), "SR-5A"),
+        lecture((2025, 1, 27), "SR-5A"),
+    ]);
+    let full = export_ics(&old, &options, None);
+    assert!(full.cancelled_ics.is_none());
+    assert_eq!(full.snapshot.events.len(), 3);
+
+    let new = course(vec![
+        lecture((2025, 1, 13), "SR-5A"),
+        lecture((2025, 1, 20), "LT-1"),
+    ]);
+    let delta = export_ics(&new, &options, Some(&full.snapshot));
+
+    assert!(delta.ics.contains("METHOD:PUBLISH"));
+    assert_eq!(delta.ics.matches("BEGIN:VEVENT").count(), 1);
+    assert!(delta.ics.contains("LOCATION:LT-1"));
+    let moved_uid = new[0].table_entries[1].uid(&new[0].course_name);
+    let before = full.snapshot.events[&moved_uid].sequence;
+    assert_eq!(delta.snapshot.events[&moved_uid].sequence, before + 1);
+    assert!(delta.ics.contains(&format!("SEQUENCE:{}", before + 1)));
+
+    let cancelled = delta.cancelled_ics.unwrap();
+    assert!(cancelled.contains("METHOD:CANCEL"));
+    assert!(cancelled.contains("STATUS:CANCELLED"));
+    assert_eq!(cancelled.matches("BEGIN:VEVENT").count(), 1);
+    assert_eq!(delta.snapshot.events.len(), 2);
+
+    let again = export_ics(&new, &options, Some(&delta.snapshot));
+    assert_eq!(again.ics.matches("BEGIN:VEVENT").count(), 0);
+    assert!(again.cancelled_ics.is_none());
+}
+
+#[test]
+fn dropped_and_waitlisted_courses_are_filtered_on_export() {
+    let mut courses = course(vec![lecture((2025, 1, 13), "SR-5A")]);
+    courses[0].enrollment_status = Some(EnrollmentStatus::Dropped);
+    let mut options = ExportOptions::default();
+    let all = export_ics(&courses, &options, None);
+    assert_eq!(all.ics.matches("BEGIN:VEVENT").count(), 1);
+
+    options.filter_dropped = true;
+    let filtered = export_ics(&courses, &options, None);
+    assert_eq!(filtered.ics.matches("BEGIN:VEVENT").count(), 0);
+    // filtering a course out of an incremental export cancels its events
+    options.incremental = true;
+    let delta = export_ics(&courses, &options, Some(&all.snapshot));
+    assert!(delta.cancelled_ics.unwrap().contains("STATUS:CANCELLED"));
+}
+
+#[test]
+fn full_export_only_raises_the_sequence_of_changed_events() {
+    let options = ExportOptions::default();
+    let old = course(vec![
+        lecture((2025, 1, 13), "SR-5A"),
+        lecture((2025, 1, 20), "SR-5A"),
+    ]);
+    let first = export_ics(&old, &options, None);
+    assert_eq!(first.ics.matches("SEQUENCE:0").count(), 2);
+
+    let same = export_ics(&old, &options, Some(&first.snapshot));
+    assert_eq!(same.ics.matches("BEGIN:VEVENT").count(), 2);
+    assert_eq!(same.ics.matches("SEQUENCE:0").count(), 2);
+
+    let new = course(vec![
+        lecture((2025, 1, 13), "SR-5A"),
+        lecture((2025, 1, 20), "LT-1"),
+    ]);
+    let changed = export_ics(&new, &options, Some(&same.snapshot));
+    assert_eq!(changed.ics.matches("BEGIN:VEVENT").count(), 2);
+    assert_eq!(changed.ics.matches("SEQUENCE:0").count(), 1);
+    assert_eq!(changed.ics.matches("SEQUENCE:1").count(), 1);
+    assert!(changed.cancelled_ics.is_none());
+}
+
+fn uids(ics: &str) -> Vec<&str> {
+    ics.lines().filter(|l| l.starts_with("UID:")).collect()
+}
+
+#[test]
+fn uids_are_stable_across_scrapes_and_exports() {
+    let fixture = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/normal_term.html");
+    let html = std::fs::read_to_string(fixture).unwrap();
+    let (_, first) = extract_timetable_from_html(html.clone(), &CollectingSink::new()).unwrap();
+    let (_, second) = extract_timetable_from_html(html, &CollectingSink::new()).unwrap();
+    let options = ExportOptions::default();
+
+    let exported = courses_to_ics(&first, &options);
+    assert!(!uids(&exported).is_empty());
+    assert_eq!(uids(&exported), uids(&courses_to_ics(&first, &options)));
+    assert_eq!(uids(&exported), uids(&courses_to_ics(&second, &options)));
+}
+
+// UIDs already in users' calendars, a change here duplicates every event on the next import
+#[test]
+fn uid_is_pinned() {
+    let slot = lecture((2025, 1, 13), "SR-5A");
+    assert_eq!(
+        slot.uid("CSC1107 - Operating Systems"),
+        "cfb98b26-858b-52c7-b06f-914d13766ab6@export-sit-timetable"
+    );
+}
+
+#[test]
+fn moved_slot_gets_a_new_uid() {
+    let name = "CSC1107 - Operating Systems";
+    let original = lecture((2025, 1, 13), "SR-5A");
+    // a new venue is an update of the same event
+    let new_venue = lecture((2025, 1, 13), "LT-1");
+    assert_eq!(original.uid(name), new_venue.uid(name));
+
+    let new_time = entry((2025, 1, 13), 14, "SR-5A", "Alice Tan");
+    assert_ne!(original.uid(name), new_time.uid(name));
+    let mut new_section = original.clone();
+    new_section.class_section = "P2".to_string();
+    assert_ne!(original.uid(name), new_section.uid(name));
+}
+
+#[test]
+fn portal_times_are_exported_in_singapore_time() {
+    let (start, end) = try_parse_string_to_start_end_dt("Mo 9:00AM - 11:00AM 13/01/2025").unwrap();
+    assert_eq!(start.timezone(), PORTAL_TZ);
+    assert_eq!(start.to_utc().to_rfc3339(), "2025-01-13T01:00:00+00:00");
+    let mut slot = lecture((2025, 1, 13), "SR-5A");
+    slot.start_datetime = start;
+    slot.end_datetime = end;
+
+    // times saved with another offset are read back in Singapore time
+    let json = serde_json::to_string(&slot).unwrap();
+    assert!(json.contains("\"2025-01-13T09:00:00+08:00\""));
+    let json = json.replace("2025-01-13T09:00:00+08:00", "2025-01-13T01:00:00Z");
+    let restored: TimeTableEntry = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored.start_datetime, start);
+    assert_eq!(restored.start_datetime.timezone(), PORTAL_TZ);
+
+    let ics = courses_to_ics(&course(vec![slot]), &ExportOptions::default());
+    let vtimezone = ics.find("BEGIN:VTIMEZONE").unwrap();
+    assert!(vtimezone < ics.find("BEGIN:VEVENT").unwrap());
+    assert!(ics.contains("TZID:Asia/Singapore\r\n"));
+    assert!(ics.contains("TZOFFSETTO:+0800\r\n"));
+    assert!(ics.contains("DTSTART;TZID=Asia/Singapore:20250113T090000\r\n"));
+    assert!(ics.contains("DTEND;TZID=Asia/Singapore:20250113T110000\r\n"));
+}
+
+#[test]
+fn csv_export_quotes_commas_and_quotes() {
+    let mut courses = course(vec![lecture((2025, 1, 13), "SR-5A, Level 2")]);
+    courses[0].title = Some("Operating \"Systems\"".to_string());
+    let csv = ExportFormat::Csv
+        .exporter()
+        .export(&courses, &ExportOptions::default())
+        .unwrap();
+    let mut lines = csv.lines();
+    assert!(lines
+        .next()
+        .unwrap()
+        .starts_with("course,subject,catalog_number,title,"));
+    let row = lines.next().unwrap();
+    assert!(row.starts_with("CSC1107 - Operating Systems,,,\"Operating \"\"Systems\"\"\","));
+    assert!(row.contains(",2025-01-13,Mon,09:00,11:00,\"SR-5A, Level 2\",Alice Tan"));
+    assert!(lines.next().is_none());
+}
+
+#[test]
+fn json_export_round_trips_the_filtered_courses() {
+    let mut courses = course(vec![lecture((2025, 1, 13), "SR-5A")]);
+    courses.extend(course(vec![lecture((2025, 1, 14), "LT-1")]));
+    courses[1].enrollment_status = Some(EnrollmentStatus::Waitlisted);
+    let options = ExportOptions {
+        filter_waitlisted: true,
+        ..Default::default()
+    };
+    let json = ExportFormat::Json
+        .exporter()
+        .export(&courses, &options)
+        .unwrap();
+    let exported: Vec<CourseInfo> = serde_json::from_str(&json).unwrap();
+    assert_eq!(exported.len(), 1);
+    assert_eq!(exported[0].course_name, courses[0].course_name);
+    assert_eq!(
+        exported[0].table_entries[0].start_datetime,
+        courses[0].table_entries[0].start_datetime
+    );
+}
+
+#[test]
+fn html_export_escapes_titles_and_venues() {
+    let mut courses = course(vec![lecture((2025, 1, 13), "Lab <A> & \"B\"")]);
+    courses[0].course_name = "CSC1107 - <script>Systems</script>".to_string();
+    let html = ExportFormat::Html
+        .exporter()
+        .export(&courses, &ExportOptions::default())
+        .unwrap();
+    assert!(html.contains("<h2>Week of 13 Jan 2025</h2>"));
+    assert!(html.contains("<th>Fri 17/01</th>"));
+    assert!(!html.contains("<th>Sat"));
+    assert!(!html.contains("<script>"));
+    assert!(html.contains("CSC1107 - &lt;script&gt;Systems&lt;/script&gt; - P1 - Lecture"));
+    assert!(html.contains("Lab &lt;A&gt; &amp; &quot;B&quot;"));
+}
+
+#[test]
+fn alarm_and_entry_type_styles_are_exported() {
+    let courses = course(vec![
+        lecture((2025, 1, 13), "SR-5A"),
+        TimeTableEntry {
+            entry_type: EntryType::Lab,
+            ..lecture((2025, 1, 14), "LAB-2")
+        },
+    ]);
+    let mut options = ExportOptions {
+        alarm_minutes_before: Some(15),
+        ..Default::default()
+    };
+    options.entry_type_styles.insert(
+        "Lecture".to_string(),
+        EntryTypeStyle {
+            color: Some("teal".to_string()),
+            category: Some("Lectures".to_string()),
+        },
+    );
+    // not a CSS3 colour name, so not valid in COLOR
+    options.entry_type_styles.insert(
+        "Lab".to_string(),
+        EntryTypeStyle {
+            color: Some("#ff8800".to_string()),
+            category: Some("Labs".to_string()),
+        },
+    );
+    let ics = courses_to_ics(&courses, &options);
+
+    assert_eq!(ics.matches("BEGIN:VALARM").count(), 2);
+    assert!(ics.contains("ACTION:DISPLAY\r\n"));
+    assert!(ics.contains("TRIGGER;RELATED=START:-PT900S\r\n"));
+    assert!(ics.contains("COLOR:teal\r\n"));
+    assert!(ics.contains("CATEGORIES:Lectures\r\n"));
+    assert!(ics.contains("CATEGORIES:Labs\r\n"));
+    assert!(!ics.contains("#ff8800"));
+}
@@ -0,0 +1,13 @@
+#![cfg(feature = "bundled-chromium")]
+
+use export_sit_timetable_lib::fetcher::{pinned_snapshot, PLATFORMS};
+
+#[test]
+fn every_platform_has_a_pinned_snapshot() {
+    let unpinned = PLATFORMS
+        .iter()
+        .filter(|p| pinned_snapshot(p.name).is_none())
+        .map(|p| p.name)
+        .collect::<Vec<_>>();
+    assert!(unpinned.is_empty(), "no snapshot pinned for {:?}", unpinned);
+}
@@ -0,0 +1,240 @@
+//! Golden-file tests for `extract_timetable_from_html`.
+//!
+//! Each `tests/fixtures/<name>.html` is an anonymised copy of the in4SIT study list page and
+//! `<name>.golden` holds the rendered parse result. Run with `UPDATE_GOLDEN=1` to regenerate
+//! the golden files after an intended parser change, then review the diff.
+
+use export_sit_timetable_lib::models::{CourseInfo, EnrollmentStatus, EntryType, ScrapResult};
+use export_sit_timetable_lib::progress::CollectingSink;
+use export_sit_timetable_lib::scrap::{
+    displayed_term, extract_timetable_from_html, extract_timetables, import_saved_html,
+    parse_terms, ScrapError,
+};
+use std::fmt::Write;
+use std::path::PathBuf;
+
+fn fixture_path(file: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join(file)
+}
+
+fn render(result: &ScrapResult, courses: &[CourseInfo]) -> String {
+    let mut out = String::new();
+    writeln!(out, "term: {}", result.term.as_deref().unwrap_or("-")).unwrap();
+    writeln!(
+        out,
+        "skipped_unknown_course_count: {}",
+        result.skipped_unknown_course_count
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "skipped_table_entry_count: {}",
+        result.skipped_table_entry_count
+    )
+    .unwrap();
+    writeln!(out, "errors_present: {}", result.errors_present).unwrap();
+    for course in courses {
+        writeln!(out, "\n{}", course.course_name).unwrap();
+        writeln!(
+            out,
+            "  [{} | {} | {} | {:?} | {} | {}]",
+            course.subject.as_deref().unwrap_or("-"),
+            course.catalog_number.as_deref().unwrap_or("-"),
+            course.title.as_deref().unwrap_or("-"),
+            course.units,
+            course.grading_basis.as_deref().unwrap_or("-"),
+            course.enrollment_status.as_ref().map_or("-", |s| s.label()),
+        )
+        .unwrap();
+        for e in &course.table_entries {
+            writeln!(
+                out,
+                "  {} | {} | {:?} ({}) | {} - {} | {} | {}",
+                e.class_number.as_deref().unwrap_or("-"),
+                e.class_section,
+                e.entry_type,
+                e.component,
+                e.start_datetime.format("%Y-%m-%d %a %H:%M"),
+                e.end_datetime.format("%H:%M"),
+                e.location,
+                e.instructors.join(", "),
+            )
+            .unwrap();
+        }
+    }
+    out
+}
+
+fn parse_fixture(name: &str) -> (ScrapResult, Vec<CourseInfo>, CollectingSink) {
+    let html = std::fs::read_to_string(fixture_path(&format!("{name}.html"))).unwrap();
+    let sink = CollectingSink::new();
+    let (result, courses) = extract_timetable_from_html(html, &sink).unwrap();
+
+    let actual = render(&result, &courses);
+    let golden_path = fixture_path(&format!("{name}.golden"));
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::write(&golden_path, &actual).unwrap();
+    }
+    let expected = std::fs::read_to_string(&golden_path).unwrap_or_else(|_| {
+        panic!(
+            "missing {}, run with UPDATE_GOLDEN=1",
+            golden_path.display()
+        )
+    });
+    assert_eq!(actual, expected, "{name} does not match its golden file");
+    (result, courses, sink)
+}
+
+#[test]
+fn normal_term() {
+    let (result, courses, _) = parse_fixture("normal_term");
+    assert_eq!(result.skipped_unknown_course_count, 0);
+    assert_eq!(result.skipped_table_entry_count, 0);
+    assert!(!result.errors_present);
+    assert_eq!(courses.len(), 2);
+    assert_eq!(courses[0].table_entries.len(), 4);
+    assert_eq!(courses[1].table_entries.len(), 4);
+}
+
+#[test]
+fn continuation_rows_inherit_section_and_component() {
+    let (_, courses, _) = parse_fixture("normal_term");
+    let second_lecture = &courses[0].table_entries[1];
+    assert_eq!(second_lecture.class_section, "P1");
+    assert_eq!(format!("{:?}", second_lecture.entry_type), "Lecture");
+    let second_tutorial = &courses[0].table_entries[3];
+    assert_eq!(second_tutorial.class_section, "P1-T3");
+    assert_eq!(format!("{:?}", second_tutorial.entry_type), "Tutorial");
+}
+
+#[test]
+fn dropped_and_waitlisted_classes_are_parsed() {
+    let (result, courses, _) = parse_fixture("dropped_waitlisted");
+    assert_eq!(result.skipped_unknown_course_count, 0);
+    assert_eq!(result.skipped_table_entry_count, 0);
+    assert!(!result.errors_present);
+    assert_eq!(courses.len(), 3);
+}
+
+#[test]
+fn course_header_is_parsed_into_fields() {
+    let (_, courses, _) = parse_fixture("dropped_waitlisted");
+    let design = &courses[2];
+    assert_eq!(design.subject.as_deref(), Some("UDE"));
+    assert_eq!(design.catalog_number.as_deref(), Some("2222"));
+    assert_eq!(design.title.as_deref(), Some("Design Thinking"));
+    assert_eq!(design.units, Some(4.0));
+    assert_eq!(design.grading_basis.as_deref(), Some("Pass/Fail"));
+    assert_eq!(design.enrollment_status, Some(EnrollmentStatus::Waitlisted));
+}
+
+#[test]
+fn tba_rows_are_skipped() {
+    let (result, courses, sink) = parse_fixture("tba_rows");
+    assert_eq!(result.skipped_unknown_course_count, 0);
+    assert_eq!(result.skipped_table_entry_count, 2);
+    assert!(!result.errors_present);
+    assert_eq!(courses[0].table_entries.len(), 1);
+    assert!(sink
+        .messages()
+        .iter()
+        .any(|m| m.contains("meeting info not available")));
+}
+
+#[test]
+fn multiple_instructors_are_split() {
+    let (_, courses, _) = parse_fixture("multi_instructor");
+    let entries = &courses[0].table_entries;
+    assert_eq!(entries[0].instructors, ["Jasmine Ong", "Dr Kumar Suresh"]);
+    assert_eq!(
+        entries[1].instructors,
+        ["Jasmine Ong", "Liam Goh", "Mei Ling Ho"]
+    );
+}
+
+#[test]
+fn unknown_components_and_broken_courses_are_reported() {
+    let (result, courses, sink) = parse_fixture("unknown_component");
+    assert_eq!(result.skipped_unknown_course_count, 2);
+    assert_eq!(result.skipped_table_entry_count, 1);
+    assert!(result.errors_present);
+    assert_eq!(courses.len(), 1);
+    let entries = &courses[0].table_entries;
+    assert_eq!(entries[0].entry_type, EntryType::Seminar);
+    assert_eq!(
+        entries[1].entry_type,
+        EntryType::Other("Field Trip".to_string())
+    );
+    assert_eq!(entries[1].class_number.as_deref(), Some("6102"));
+    assert!(sink
+        .messages()
+        .iter()
+        .any(|m| m.contains("unknown entry type")));
+}
+
+#[test]
+fn courses_of_several_terms_are_tagged_with_their_term() {
+    let pages = ["normal_term.html", "tba_rows.html"]
+        .map(|f| std::fs::read_to_string(fixture_path(f)).unwrap())
+        .to_vec();
+    let sink = CollectingSink::new();
+    let (result, courses) = extract_timetables(pages, &sink).unwrap();
+    assert_eq!(
+        result.term.as_deref(),
+        Some("Trimester 2 2024-25, Trimester 3 2024-25")
+    );
+    assert_eq!(result.skipped_table_entry_count, 2);
+    let terms = courses
+        .iter()
+        .map(|c| c.term.as_deref().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(terms.first(), Some(&"Trimester 2 2024-25"));
+    assert_eq!(terms.last(), Some(&"Trimester 3 2024-25"));
+}
+
+#[test]
+fn missing_study_list_table_is_an_error() {
+    let sink = CollectingSink::new();
+    let err =
+        extract_timetable_from_html("<html><body></body></html>".to_string(), &sink).unwrap_err();
+    assert!(matches!(err, ScrapError::HtmlParseError(_)));
+}
+
+#[test]
+fn saved_outer_portal_page_asks_for_the_study_list_frame() {
+    let path = fixture_path("portal_frame.html");
+    let sink = CollectingSink::new();
+    let err = tauri::async_runtime::block_on(import_saved_html(path.to_str().unwrap(), &sink))
+        .unwrap_err();
+    assert!(matches!(&err, ScrapError::FileError(msg) if msg.contains("open the frame")));
+}
+
+#[test]
+fn terms_are_listed_from_the_selection_page() {
+    let html = std::fs::read_to_string(fixture_path("term_selection.html")).unwrap();
+    let terms = parse_terms(&html);
+    let names = terms.iter().map(|t| t.name.as_str()).collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        [
+            "Trimester 2 2024-25",
+            "Trimester 3 2024-25",
+            "Trimester 1 2025-26"
+        ]
+    );
+    assert_eq!(terms[2].index, 2);
+    assert_eq!(terms[2].career.as_deref(), Some("Undergraduate"));
+    assert_eq!(terms[2].institution, None);
+
+    let study_list = std::fs::read_to_string(fixture_path("normal_term.html")).unwrap();
+    assert!(parse_terms(&study_list).is_empty());
+    let shown = displayed_term(&study_list).unwrap();
+    assert_eq!(shown.name, "Trimester 2 2024-25");
+    assert_eq!(
+        shown.institution.as_deref(),
+        Some("Singapore Institute of Technology")
+    );
+}
@@ -0,0 +1,52 @@
+mod common;
+
+use common::{at, lecture};
+use export_sit_timetable_lib::recurrence::compress_weekly;
+
+#[test]
+fn weekly_meetings_with_recess_week_become_one_series() {
+    let entries = [
+        lecture((2025, 1, 13), "SR-5A"),
+        lecture((2025, 1, 20), "SR-5A"),
+        // 27/01 is recess week
+        lecture((2025, 2, 3), "SR-5A"),
+        lecture((2025, 2, 10), "SR-5A"),
+    ];
+    let series = compress_weekly(&entries);
+    assert_eq!(series.len(), 1);
+    assert_eq!(
+        series[0].rrule().as_deref(),
+        Some("FREQ=WEEKLY;INTERVAL=1;COUNT=5")
+    );
+    assert_eq!(series[0].skipped, [at((2025, 1, 27), 9)]);
+}
+
+#[test]
+fn fortnightly_meetings_use_an_interval() {
+    let entries = [
+        lecture((2025, 1, 13), "SR-5A"),
+        lecture((2025, 1, 27), "SR-5A"),
+        lecture((2025, 2, 10), "SR-5A"),
+    ];
+    let series = compress_weekly(&entries);
+    assert_eq!(series.len(), 1);
+    assert_eq!(
+        series[0].rrule().as_deref(),
+        Some("FREQ=WEEKLY;INTERVAL=2;COUNT=3")
+    );
+    assert!(series[0].skipped.is_empty());
+}
+
+#[test]
+fn a_change_of_venue_starts_a_new_series() {
+    let entries = [
+        lecture((2025, 1, 13), "SR-5A"),
+        lecture((2025, 1, 20), "SR-5A"),
+        lecture((2025, 1, 27), "LT-1"),
+    ];
+    let series = compress_weekly(&entries);
+    assert_eq!(series.len(), 2);
+    assert!(series[0].is_recurring());
+    assert!(!series[1].is_recurring());
+    assert_eq!(series[1].rrule(), None);
+}
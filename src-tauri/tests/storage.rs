@@ -0,0 +1,37 @@
+use chrono::Utc;
+use export_sit_timetable_lib::helper::PORTAL_TZ;
+use export_sit_timetable_lib::models::{SavedTimetable, ScrapeMetadata};
+use export_sit_timetable_lib::progress::CollectingSink;
+use export_sit_timetable_lib::scrap::extract_timetable_from_html;
+use export_sit_timetable_lib::storage::{clear_last_scrape, load_last_scrape, save_last_scrape};
+use std::path::PathBuf;
+
+#[test]
+fn saved_timetable_round_trips() {
+    let fixture = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/normal_term.html");
+    let html = std::fs::read_to_string(fixture).unwrap();
+    let (result, courses) = extract_timetable_from_html(html, &CollectingSink::new()).unwrap();
+    let saved = SavedTimetable {
+        metadata: ScrapeMetadata {
+            scraped_at: Utc::now(),
+            term: result.term,
+            options: None,
+        },
+        courses,
+    };
+
+    let dir = std::env::temp_dir().join(format!("sit-timetable-storage-{}", std::process::id()));
+    tauri::async_runtime::block_on(save_last_scrape(&dir, &saved)).unwrap();
+    let loaded = load_last_scrape(&dir).unwrap();
+    tauri::async_runtime::block_on(clear_last_scrape(&dir)).unwrap();
+    assert!(load_last_scrape(&dir).is_none());
+    let _ = std::fs::remove_dir(&dir);
+
+    assert_eq!(loaded.metadata.term.as_deref(), Some("Trimester 2 2024-25"));
+    assert_eq!(loaded.courses.len(), saved.courses.len());
+    let original = &saved.courses[0].table_entries[0];
+    let restored = &loaded.courses[0].table_entries[0];
+    assert_eq!(restored.start_datetime, original.start_datetime);
+    assert_eq!(restored.start_datetime.timezone(), PORTAL_TZ);
+    assert_eq!(restored.instructors, original.instructors);
+}
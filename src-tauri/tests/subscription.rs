@@ -0,0 +1,69 @@
+use export_sit_timetable_lib::models::SubscriptionOptions;
+use export_sit_timetable_lib::subscription::{new_token, start_subscription_server};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+async fn get(port: u16, path: &str) -> String {
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+    let request = format!("GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path);
+    stream.write_all(request.as_bytes()).await.unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    response
+}
+
+#[test]
+fn serves_the_calendar_only_at_the_token_url() {
+    tauri::async_runtime::block_on(async {
+        let options = SubscriptionOptions {
+            port: 0,
+            allow_lan: false,
+        };
+        let token = new_token();
+        let server = start_subscription_server(&options, &token, || async {
+            "BEGIN:VCALENDAR\r\nEND:VCALENDAR\r\n".to_string()
+        })
+        .await
+        .unwrap();
+        assert_eq!(server.token, token);
+        let url = server.url.strip_prefix("http://127.0.0.1:").unwrap();
+        let (port, path) = url.split_at(url.find('/').unwrap());
+        let port = port.parse().unwrap();
+
+        let response = get(port, &format!("{}?refresh=1", path)).await;
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.contains("Content-Type: text/calendar"));
+        assert!(response.ends_with("BEGIN:VCALENDAR\r\nEND:VCALENDAR\r\n"));
+
+        let response = get(port, "/timetable.ics").await;
+        assert!(response.starts_with("HTTP/1.1 404 Not Found"));
+        assert!(response.contains("Content-Type: text/plain"));
+        server.stop().await;
+    });
+}
+
+#[test]
+fn restarting_with_the_saved_token_and_port_keeps_the_url() {
+    tauri::async_runtime::block_on(async {
+        let options = SubscriptionOptions {
+            port: 0,
+            allow_lan: false,
+        };
+        let calendar = || async { String::new() };
+        let server = start_subscription_server(&options, &new_token(), calendar)
+            .await
+            .unwrap();
+        let (url, token, port) = (server.url.clone(), server.token.clone(), server.port);
+        server.stop().await;
+
+        let options = SubscriptionOptions {
+            port,
+            allow_lan: false,
+        };
+        let server = start_subscription_server(&options, &token, calendar)
+            .await
+            .unwrap();
+        assert_eq!(server.url, url);
+        server.stop().await;
+    });
+}
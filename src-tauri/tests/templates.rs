@@ -0,0 +1,48 @@
+mod common;
+
+use common::{course, lecture};
+use export_sit_timetable_lib::helper::render_template;
+
+#[test]
+fn placeholders_are_filled_in() {
+    let mut courses = course(vec![lecture((2025, 1, 13), "SR-5A")]);
+    let entry = &mut courses[0].table_entries[0];
+    entry.instructors.push("Benjamin Lee".to_string());
+    let entry = entry.clone();
+    let rendered = render_template(
+        "{course_code}|{title}|{section}|{type}|{location}|{instructors}|{instructor_lines}",
+        &courses[0],
+        &entry,
+    );
+    assert_eq!(
+        rendered,
+        "CSC1107|Operating Systems|P1|Lecture|SR-5A|Alice Tan, Benjamin Lee|Alice Tan\nBenjamin Lee"
+    );
+    assert_eq!(
+        render_template("{course}", &courses[0], &entry),
+        "CSC1107 - Operating Systems"
+    );
+}
+
+#[test]
+fn parsed_course_fields_win_over_the_course_name() {
+    let mut courses = course(vec![lecture((2025, 1, 13), "SR-5A")]);
+    courses[0].subject = Some("CSC".to_string());
+    courses[0].catalog_number = Some("1107".to_string());
+    courses[0].title = Some("Operating Systems and Networks".to_string());
+    let entry = &courses[0].table_entries[0];
+    assert_eq!(
+        render_template("{course_code}: {title}", &courses[0], entry),
+        "CSC1107: Operating Systems and Networks"
+    );
+}
+
+#[test]
+fn unknown_placeholders_are_left_as_they_are() {
+    let courses = course(vec![lecture((2025, 1, 13), "SR-5A")]);
+    let entry = &courses[0].table_entries[0];
+    assert_eq!(
+        render_template("{section} {room} {", &courses[0], entry),
+        "P1 {room} {"
+    );
+}
@@ -0,0 +1,60 @@
+use chromiumoxide::cdp::browser_protocol::network::RequestId;
+use export_sit_timetable_lib::scrap::ScrapError;
+use export_sit_timetable_lib::wait::{wait_until, NetworkActivity, NetworkMonitor, WaitOptions};
+use futures::stream::{self, StreamExt};
+use std::time::Duration;
+
+fn quick() -> WaitOptions {
+    WaitOptions {
+        timeout: Duration::from_millis(200),
+        first_delay: Duration::from_millis(5),
+        max_delay: Duration::from_millis(20),
+    }
+}
+
+#[test]
+fn waits_until_the_condition_holds() {
+    let mut checks = 0;
+    let found = tauri::async_runtime::block_on(wait_until("the third check", &quick(), || {
+        checks += 1;
+        let done = checks == 3;
+        async move { Ok(done.then_some("found")) }
+    }));
+    assert_eq!(found.unwrap(), "found");
+    assert_eq!(checks, 3);
+}
+
+#[test]
+fn timeout_names_the_condition() {
+    let result =
+        tauri::async_runtime::block_on(wait_until("#never", &quick(), || async { Ok(None::<()>) }));
+    match result {
+        Err(ScrapError::Timeout(msg)) => assert!(msg.contains("#never"), "{}", msg),
+        other => panic!("expected a timeout, got {:?}", other),
+    }
+}
+
+// requests that start and never finish, on a page that stays open
+fn never_finishing(count: usize) -> NetworkMonitor {
+    let started = (0..count)
+        .map(|i| NetworkActivity::Started(RequestId::new(i.to_string())))
+        .collect::<Vec<_>>();
+    NetworkMonitor::from_activity(stream::iter(started).chain(stream::pending()))
+}
+
+#[test]
+fn long_polling_requests_do_not_block_network_idle() {
+    let options = quick();
+    let idle = never_finishing(2).wait_for_idle(Duration::from_millis(20), &options);
+    assert!(tauri::async_runtime::block_on(idle).is_ok());
+}
+
+#[test]
+fn busy_network_times_out() {
+    let options = quick();
+    let idle = never_finishing(3).wait_for_idle(Duration::from_millis(20), &options);
+    match tauri::async_runtime::block_on(idle) {
+        Err(ScrapError::Timeout(msg)) => assert!(msg.contains("network"), "{}", msg),
+        other => panic!("expected a timeout, got {:?}", other),
+    }
+}
@@ -10,8 +10,6 @@ function Home() {
     const [isLoading, setIsLoading] = createSignal(false);
     const [logs, setLogs] = createSignal<LogEvent[]>([]);
     const [debugMode, setDebugMode] = createSignal(false);
-    const [filterDropped, setFilterDropped] = createSignal(true);
-    const [filterWaitlisted, setFilterWaitlisted] = createSignal(true);
     const [installedVersion, setInstalledVersion] = createSignal("");
 
     const navigate = useNavigate();
@@ -49,7 +47,7 @@ function Home() {
         try {
             setIsLoading(true)
             let options: ScrapOptions = {
-                debug_mode: debugMode(), filter_dropped: filterDropped(), filter_waitlisted: filterWaitlisted()
+                debug_mode: debugMode()
             }
             let result = await invoke<ScrapResult>("handle_credentials", {
                 username: username(),
@@ -110,39 +108,6 @@ function Home() {
                         <fieldset>
                             <legend class="sr-only">Options</legend>
                             <div class="space-y-5">
-                                <div class="relative flex items-start">
-                                    <div class="flex h-6 items-center">
-                                        <input id="filter-dropped" aria-describedby="filter-dropped-description"
-                                               name="filter-dropped"
-                                               type="checkbox"
-                                               checked={filterDropped()}
-                                               onChange={(e) => setFilterDropped(e.currentTarget.checked)}
-                                               class="h-4 w-4 rounded border-gray-300 text-indigo-600 focus:ring-indigo-600"/>
-                                    </div>
-                                    <div class="ml-3 text-sm leading-6">
-                                        <label for="filter-dropped" class="font-medium text-gray-500 mr-1">Filter
-                                            dropped
-                                            modules</label>
-                                        <span id="filter-dropped-description" class="text-gray-200"><span
-                                            class="sr-only">Filter dropped modules </span>Hide dropped modules</span>
-                                    </div>
-                                </div>
-                                <div class="relative flex items-start">
-                                    <div class="flex h-6 items-center">
-                                        <input id="filter-waitlist" aria-describedby="candidates-description"
-                                               name="candidates" type="checkbox"
-                                               checked={filterWaitlisted()}
-                                               onChange={(e) => setFilterWaitlisted(e.currentTarget.checked)}
-                                               class="h-4 w-4 rounded border-gray-300 text-indigo-600 focus:ring-indigo-600"/>
-                                    </div>
-                                    <div class="ml-3 text-sm leading-6">
-                                        <label for="filter-waitlist" class="font-medium text-gray-500 mr-1">Filter
-                                            waitlisted
-                                            modules</label>
-                                        <span id="filter-waitlisted-description" class="text-gray-200"><span
-                                            class="sr-only">Filter waitlisted modules </span>Hide waitlisted modules</span>
-                                    </div>
-                                </div>
                                 <div class="relative flex items-start">
                                     <div class="flex h-6 items-center">
                                         <input id="debug" aria-describedby="debug-description" name="debug"
@@ -1,5 +1,5 @@
 import {useLocation} from "@solidjs/router";
-import {JSX} from "solid-js";
+import {createSignal, JSX} from "solid-js";
 import {message, save} from '@tauri-apps/plugin-dialog';
 import {invoke} from "@tauri-apps/api/core";
 
@@ -12,6 +12,8 @@ export default function ShowResults() {
 
     const location = useLocation<StateValues>();
     const {logs, result} = location.state || {};
+    const [filterDropped, setFilterDropped] = createSignal(true);
+    const [filterWaitlisted, setFilterWaitlisted] = createSignal(true);
 
     const decodeLogLevel = (level: number): JSX.Element | null => {
         if (level == 1) {
@@ -46,8 +48,11 @@ export default function ShowResults() {
                 },
             ],
         });
+        let options: ExportOptions = {
+            filter_dropped: filterDropped(), filter_waitlisted: filterWaitlisted()
+        }
         try {
-            await invoke("export_to_ics", {path: path})
+            await invoke("export_to_ics", {path: path, options: options})
             await message("Export complete", { title: 'Export success', kind: 'info' });
         } catch (error) {
             // @ts-ignore
@@ -120,6 +125,44 @@ export default function ShowResults() {
                                 </dd>
                             </div>
                         </dl>
+                        <fieldset class="my-4">
+                            <legend class="sr-only">Export options</legend>
+                            <div class="space-y-5">
+                                <div class="relative flex items-start">
+                                    <div class="flex h-6 items-center">
+                                        <input id="filter-dropped" aria-describedby="filter-dropped-description"
+                                               name="filter-dropped"
+                                               type="checkbox"
+                                               checked={filterDropped()}
+                                               onChange={(e) => setFilterDropped(e.currentTarget.checked)}
+                                               class="h-4 w-4 rounded border-gray-300 text-indigo-600 focus:ring-indigo-600"/>
+                                    </div>
+                                    <div class="ml-3 text-sm leading-6">
+                                        <label for="filter-dropped" class="font-medium text-gray-500 mr-1">Filter
+                                            dropped
+                                            modules</label>
+                                        <span id="filter-dropped-description" class="text-gray-200"><span
+                                            class="sr-only">Filter dropped modules </span>Hide dropped modules</span>
+                                    </div>
+                                </div>
+                                <div class="relative flex items-start">
+                                    <div class="flex h-6 items-center">
+                                        <input id="filter-waitlist" aria-describedby="candidates-description"
+                                               name="candidates" type="checkbox"
+                                               checked={filterWaitlisted()}
+                                               onChange={(e) => setFilterWaitlisted(e.currentTarget.checked)}
+                                               class="h-4 w-4 rounded border-gray-300 text-indigo-600 focus:ring-indigo-600"/>
+                                    </div>
+                                    <div class="ml-3 text-sm leading-6">
+                                        <label for="filter-waitlist" class="font-medium text-gray-500 mr-1">Filter
+                                            waitlisted
+                                            modules</label>
+                                        <span id="filter-waitlisted-description" class="text-gray-200"><span
+                                            class="sr-only">Filter waitlisted modules </span>Hide waitlisted modules</span>
+                                    </div>
+                                </div>
+                            </div>
+                        </fieldset>
                         {result ? (
                             <button type="button"
                                     onclick={handleExport}
@@ -5,9 +5,12 @@ interface LogEvent {
 }
 
 interface ScrapOptions {
+    debug_mode: boolean;
+}
+
+interface ExportOptions {
     filter_dropped: boolean;
     filter_waitlisted: boolean;
-    debug_mode: boolean;
 }
 
 interface ScrapResult {